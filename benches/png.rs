@@ -0,0 +1,88 @@
+//! Throughput of the core operations, on in-memory PNGs with small to large payloads
+
+use std::str::FromStr;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use pngchat::{Chunk, ChunkType, Png};
+
+/// Payload sizes in bytes: a short message, a small file and a large attachment
+const SIZES: [usize; 3] = [64, 64 * 1024, 4 * 1024 * 1024];
+
+fn payload(size: usize) -> Vec<u8> {
+    (0..size).map(|i| (i % 251) as u8).collect()
+}
+
+fn message_chunk(size: usize) -> Chunk {
+    Chunk::new(ChunkType::from_str("ruSt").unwrap(), payload(size))
+}
+
+fn testing_png(size: usize) -> Png {
+    Png::from_chunks(vec![
+        Chunk::from_strings("IHDR", "header").unwrap(),
+        Chunk::new(ChunkType::from_str("IDAT").unwrap(), payload(size)),
+        message_chunk(size),
+        Chunk::from_strings("IEND", "").unwrap(),
+    ])
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for size in SIZES {
+        let bytes = testing_png(size).as_bytes();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &bytes, |b, bytes| {
+            b.iter(|| Png::try_from(black_box(bytes.as_slice())).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_append_chunk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("append_chunk");
+    for size in SIZES {
+        let png = testing_png(64);
+        let chunk = message_chunk(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &chunk, |b, chunk| {
+            b.iter(|| {
+                let mut png = png.clone();
+                png.append_chunk(black_box(chunk.clone()));
+                png
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_as_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("as_bytes");
+    for size in SIZES {
+        let png = testing_png(size);
+        group.throughput(Throughput::Bytes(png.as_bytes().len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &png, |b, png| {
+            b.iter(|| black_box(png).as_bytes())
+        });
+    }
+    group.finish();
+}
+
+fn bench_crc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crc");
+    for size in SIZES {
+        let chunk = message_chunk(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &chunk, |b, chunk| {
+            b.iter(|| black_box(chunk).computed_crc())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_parse,
+    bench_append_chunk,
+    bench_as_bytes,
+    bench_crc
+);
+criterion_main!(benches);
@@ -2,7 +2,7 @@
 
 use std::path::PathBuf;
 
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgEnum, Args, Parser, Subcommand};
 
 #[derive(Debug, Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -22,38 +22,443 @@ pub enum Commands {
     Remove(RemoveArgs),
     /// Print a list of PNG chunks that can be searched for messages
     Print(PrintArgs),
+    /// Move chunks placed after IEND in front of it so that IEND ends the file
+    Normalize(NormalizeArgs),
+    /// Split a payload file across several PNG files
+    Shard(ShardArgs),
+    /// Reassemble a payload split with `shard` and write it to stdout
+    Unshard(UnshardArgs),
+    /// Print the chunk at a certain position
+    Get(GetArgs),
+    /// Silently check whether a chunk type exists, exits with 0 if it does
+    Has(HasArgs),
+    /// Print the chunks again every time the file changes
+    Watch(WatchArgs),
+    /// Write a copy of a PNG file, optionally without its hidden messages
+    Export(ExportArgs),
+    /// Write the raw chunk byte stream to stdout, without the PNG signature
+    RawChunks(RawChunksArgs),
+    /// Move a chunk to another position, as listed by `print`
+    Move(MoveArgs),
+    /// Remove ancillary chunks that are exact duplicates of an earlier one
+    Dedup(DedupArgs),
+    /// Report every structural problem of a PNG file, exits with 1 if there is any
+    Lint(LintArgs),
+    /// Copy the first chunk of a type from one PNG file to the end of another
+    CopyChunk(CopyChunkArgs),
+    /// Summarize the image: dimensions, color type, bit depth, palette and chunk counts
+    Info(InfoArgs),
+    /// Check whether a string is a valid chunk type and show what its letters imply
+    ValidateType(ValidateTypeArgs),
+    /// Browse the chunks in an interactive terminal UI
+    #[cfg(feature = "tui")]
+    Tui(TuiArgs),
 }
 
-#[derive(Debug, Args, Clone)]
+#[derive(Debug, Default, Args, Clone)]
 pub struct EncodeArgs {
     /// Input PNG file path
     pub file_path: PathBuf,
-    /// Chunk Type
-    pub chunk_type: String,
-    /// Hideen message you want to put
-    pub message: String,
+    /// Chunk Type, left out with --random-type
+    #[clap(required_unless_present = "random-type")]
+    pub chunk_type: Option<String>,
+    /// Hideen message you want to put, read from stdin if omitted or `-`
+    pub message: Option<String>,
     /// If set, save PNG with hidden message in a certian place
     pub output_file: Option<PathBuf>,
+    /// Save the result under the same file name in this directory, created if missing
+    #[clap(long, value_name = "DIR", conflicts_with = "output-file")]
+    pub output_dir: Option<PathBuf>,
+    /// Allow the output file to be the input file itself
+    #[clap(long)]
+    pub overwrite: bool,
+    /// Prefix the message with its length and pad it with zeros to N bytes
+    #[clap(long, value_name = "N")]
+    pub pad_to: Option<usize>,
+    /// Protect the message with an HMAC-SHA256 computed with this key
+    #[clap(long, value_name = "KEY")]
+    pub hmac: Option<String>,
+    /// Compress the message with zlib, `auto` keeps it as is when compression does not help
+    #[clap(
+        long,
+        arg_enum,
+        value_name = "WHEN",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "always"
+    )]
+    pub compress: Option<CompressMode>,
+    /// Place the message before IEND so that APNG frame sequences stay valid
+    #[clap(long)]
+    pub apng_safe: bool,
+    /// Place the message at the front, right after IHDR, instead of appending it
+    #[clap(long, conflicts_with = "apng-safe")]
+    pub prepend: bool,
+    /// What to do when the file already has a chunk of this type
+    #[clap(long, arg_enum, value_name = "POLICY", default_value = "append")]
+    pub on_exists: OnExists,
+    /// Deprecated, same as --on-exists=replace
+    #[clap(long, conflicts_with = "on-exists")]
+    pub upsert: bool,
+    /// Store the message as standard tEXt metadata under this keyword, the chunk type must be tEXt
+    #[clap(
+        long,
+        value_name = "KEYWORD",
+        conflicts_with_all = &["hmac", "pad-to", "compress"]
+    )]
+    pub text: Option<String>,
+    /// Generate a valid custom chunk type and print it, instead of taking one as argument
+    #[clap(long, conflicts_with = "text")]
+    pub random_type: bool,
+    /// Seed for --random-type, the same seed always generates the same chunk type
+    #[clap(long, value_name = "N", requires = "random-type")]
+    pub seed: Option<u64>,
+    /// Don't print the confirmation on success
+    #[clap(short, long)]
+    pub quiet: bool,
+    /// How the message text maps to the stored bytes
+    #[clap(
+        long,
+        arg_enum,
+        value_name = "ENCODING",
+        default_value = "utf8",
+        conflicts_with = "text"
+    )]
+    pub encoding: MessageEncoding,
+    /// Append a `timestamp<TAB>command<TAB>file<TAB>chunk type` line to this file on success
+    #[clap(long, value_name = "PATH")]
+    pub log: Option<PathBuf>,
+    /// Refuse messages holding any byte outside of ASCII
+    #[clap(long)]
+    pub ascii_only: bool,
+    /// Warn when the same data is already stored in a chunk of any type
+    #[clap(long)]
+    pub dedup_check: bool,
+    /// Leave the file untouched when the same data is already stored in a chunk of any type
+    #[clap(long)]
+    pub if_absent: bool,
+    /// Keep the modification time of the file when it is modified in place
+    #[clap(long)]
+    pub preserve_mtime: bool,
 }
 
-#[derive(Debug, Args, Clone)]
+#[derive(Debug, Default, ArgEnum, Clone, Copy, PartialEq, Eq)]
+pub enum OnExists {
+    /// Fail and leave the file untouched
+    Error,
+    /// Replace the first chunk of the type in place
+    Replace,
+    /// Add another chunk of the type
+    #[default]
+    Append,
+    /// Leave the file untouched and succeed
+    Skip,
+}
+
+#[derive(Debug, ArgEnum, Clone, Copy, PartialEq, Eq)]
+pub enum CompressMode {
+    /// Always store the compressed message
+    Always,
+    /// Only store the compressed message if it is smaller
+    Auto,
+}
+
+#[derive(Debug, Default, ArgEnum, Clone, Copy, PartialEq, Eq)]
+pub enum MessageEncoding {
+    /// The UTF-8 bytes of the text
+    #[default]
+    Utf8,
+    /// One byte per character, for characters up to U+00FF
+    Latin1,
+    /// Two hex digits per byte
+    Hex,
+}
+
+#[derive(Debug, Default, Args, Clone)]
 pub struct DecodeArgs {
     /// Input PNG file path
     pub file_path: PathBuf,
     /// Chunk Type
-    pub chunk_type: String,
+    #[clap(required_unless_present = "types-file")]
+    pub chunk_type: Option<String>,
+    /// Try every chunk type listed in this file, one per line
+    #[clap(long, value_name = "FILE", conflicts_with = "chunk-type")]
+    pub types_file: Option<PathBuf>,
+    /// The message was encoded with --pad-to
+    #[clap(long)]
+    pub padded: bool,
+    /// The message was encoded with --compress
+    #[clap(long)]
+    pub compressed: bool,
+    /// Refuse to print the message unless its HMAC matches this key
+    #[clap(long, value_name = "KEY")]
+    pub verify_hmac: Option<String>,
+    /// Decode every chunk of the type instead of only the first one
+    #[clap(long)]
+    pub all: bool,
+    /// Print how many chunks of the type were found
+    #[clap(long)]
+    pub count: bool,
+    /// Only print how many chunks of the type were found, never their content; fails if none
+    #[clap(long, conflicts_with_all = &["all", "count", "nested"])]
+    pub count_only: bool,
+    /// Print the message bytes as hex instead of text
+    #[clap(long)]
+    pub hex: bool,
+    /// How the stored bytes map back to the message text, as when encoding it
+    #[clap(
+        long,
+        arg_enum,
+        value_name = "ENCODING",
+        default_value = "utf8",
+        conflicts_with = "hex"
+    )]
+    pub encoding: MessageEncoding,
+    /// Read the file even if some chunks have a wrong CRC
+    #[clap(long)]
+    pub no_crc_check: bool,
+    /// List and decode the chunks of a PNG file hidden in the chunk data, level by level
+    #[clap(long)]
+    pub nested: bool,
+    /// Copy the message to the system clipboard instead of printing it
+    #[cfg(feature = "clipboard")]
+    #[clap(long, conflicts_with_all = &["nested", "count-only"])]
+    pub clipboard: bool,
 }
 
-#[derive(Debug, Args, Clone)]
+#[derive(Debug, Default, Args, Clone)]
 pub struct RemoveArgs {
+    /// Input PNG file paths
+    #[clap(required = true)]
+    pub file_paths: Vec<PathBuf>,
+    /// Chunk Type
+    pub chunk_type: String,
+    /// Overwrite the file without asking for confirmation
+    #[clap(short, long)]
+    pub yes: bool,
+    /// Only remove a chunk placed before IEND, the ones after it are left untouched
+    #[clap(long)]
+    pub before_iend_only: bool,
+    /// Keep the modification time of the files
+    #[clap(long)]
+    pub preserve_mtime: bool,
+    /// Carry on with the remaining files when one fails and report all failures at the end
+    #[clap(long)]
+    pub keep_going: bool,
+    /// Process up to N files at once, 0 for one per CPU; needs --yes. Every file is processed
+    /// and all failures are reported at the end, as with --keep-going
+    #[clap(long, value_name = "N", requires = "yes")]
+    pub jobs: Option<usize>,
+    /// Append a `timestamp<TAB>command<TAB>file<TAB>chunk type` line to this file for each
+    /// modified file
+    #[clap(long, value_name = "PATH")]
+    pub log: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct PrintArgs {
+    /// Input PNG file paths
+    #[clap(required = true)]
+    pub file_paths: Vec<PathBuf>,
+    /// Only print the first N chunks
+    #[clap(long, value_name = "N")]
+    pub limit: Option<usize>,
+    /// When to colorize the output
+    #[clap(long, arg_enum, value_name = "WHEN", default_value = "auto")]
+    pub color: ColorMode,
+    /// Never colorize the output, same as --color=never
+    #[clap(long, conflicts_with = "color")]
+    pub no_color: bool,
+    /// Only list chunk types defined by the PNG specification
+    #[clap(long, conflicts_with = "custom-only")]
+    pub standard_only: bool,
+    /// Only list custom chunk types, which are likely hidden messages
+    #[clap(long)]
+    pub custom_only: bool,
+    /// Only list chunk types matching this regular expression
+    #[clap(long, value_name = "REGEX")]
+    pub type_regex: Option<String>,
+    /// Only list chunks holding at least N bytes of data
+    #[clap(long, value_name = "N")]
+    pub min_length: Option<u32>,
+    /// Only list chunks holding at most N bytes of data
+    #[clap(long, value_name = "N")]
+    pub max_length: Option<u32>,
+    /// List chunks with a wrong CRC and flag them instead of failing
+    #[clap(long)]
+    pub verify_crc: bool,
+    /// Order of the listed chunks, the file position is shown either way
+    #[clap(long, arg_enum, value_name = "KEY", default_value = "index")]
+    pub sort_by: SortBy,
+    /// Also show the character count of custom chunks holding UTF-8 text
+    #[clap(long)]
+    pub char_count: bool,
+    /// Show the first N bytes of each chunk's data, as text if possible or as hex
+    #[clap(long, value_name = "N")]
+    pub preview: Option<usize>,
+    /// Only print `type:crc` for each chunk, one per line, for diffing between files
+    #[clap(long)]
+    pub checksum_only: bool,
+    /// Treat the file as several PNG files concatenated together and list each image
+    #[clap(long)]
+    pub multi: bool,
+    /// Carry on with the remaining files when one fails and report all failures at the end
+    #[clap(long)]
+    pub keep_going: bool,
+    /// Process up to N files at once, 0 for one per CPU; files are then listed sorted by path
+    /// and all failures are reported at the end, as with --keep-going
+    #[clap(long, value_name = "N")]
+    pub jobs: Option<usize>,
+}
+
+#[derive(Debug, Default, ArgEnum, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    /// File order
+    #[default]
+    Index,
+    /// Largest data first
+    Size,
+    /// Alphabetical by chunk type
+    Type,
+}
+
+#[derive(Debug, Default, ArgEnum, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Colorize when stdout is a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    /// Always colorize
+    Always,
+    /// Never colorize
+    Never,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct NormalizeArgs {
     /// Input PNG file path
     pub file_path: PathBuf,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct ShardArgs {
+    /// Payload file to hide
+    pub payload: PathBuf,
+    /// PNG files receiving one shard each
+    #[clap(long, required = true, multiple_values = true)]
+    pub into: Vec<PathBuf>,
     /// Chunk Type
+    #[clap(long = "type")]
     pub chunk_type: String,
 }
 
-#[derive(Debug, Args, Clone)]
-pub struct PrintArgs {
+#[derive(Debug, Default, Args, Clone)]
+pub struct UnshardArgs {
+    /// PNG files holding the shards, in any order
+    #[clap(long, required = true, multiple_values = true)]
+    pub from: Vec<PathBuf>,
+    /// Chunk Type
+    #[clap(long = "type")]
+    pub chunk_type: String,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct GetArgs {
+    /// Input PNG file path
+    pub file_path: PathBuf,
+    /// Position of the chunk, as listed by `print`
+    #[clap(long)]
+    pub index: usize,
+    /// Print the data as hex even if it is valid text
+    #[clap(long)]
+    pub hex: bool,
+    /// Read the file even if some chunks have a wrong CRC
+    #[clap(long)]
+    pub no_crc_check: bool,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct HasArgs {
+    /// Input PNG file path
+    pub file_path: PathBuf,
+    /// Chunk Type
+    pub chunk_type: String,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct WatchArgs {
+    /// Input PNG file path
+    pub file_path: PathBuf,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct ExportArgs {
+    /// Input PNG file path
+    pub file_path: PathBuf,
+    /// Output PNG file path
+    pub output_file: PathBuf,
+    /// Drop every custom chunk, keeping the image and its standard metadata
+    #[clap(long)]
+    pub no_messages: bool,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct RawChunksArgs {
+    /// Input PNG file path
+    pub file_path: PathBuf,
+    /// Keep the 8-byte PNG signature in front of the chunks
+    #[clap(long)]
+    pub with_signature: bool,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct MoveArgs {
+    /// Input PNG file path
+    pub file_path: PathBuf,
+    /// Current position of the chunk
+    #[clap(long)]
+    pub from: usize,
+    /// Position the chunk ends up at
+    #[clap(long)]
+    pub to: usize,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct DedupArgs {
+    /// Input PNG file path
+    pub file_path: PathBuf,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct LintArgs {
+    /// Input PNG file path
+    pub file_path: PathBuf,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct InfoArgs {
+    /// Input PNG file path
+    pub file_path: PathBuf,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct ValidateTypeArgs {
+    /// Candidate chunk type, such as `ruSt`
+    pub chunk_type: String,
+}
+
+#[derive(Debug, Default, Args, Clone)]
+pub struct CopyChunkArgs {
+    /// PNG file to copy the chunk from
+    pub src: PathBuf,
+    /// PNG file to append the chunk to
+    pub dst: PathBuf,
+    /// Chunk Type
+    pub chunk_type: String,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Debug, Default, Args, Clone)]
+pub struct TuiArgs {
     /// Input PNG file path
     pub file_path: PathBuf,
 }
@@ -2,28 +2,36 @@
 //!
 //! Each chunk consists of four parts:
 //! * `Length`
-//! <br /> A **4-byte** unsigned integer giving the number of bytes in the chunk's data field.
-//! The length counts only the data field, not itself, the chunk type code, or the CRC.
+//!   <br /> A **4-byte** unsigned integer giving the number of bytes in the chunk's data field.
+//!   The length counts only the data field, not itself, the chunk type code, or the CRC.
 //!
 //! * `Chunk Type`
-//! <br /> See [Chunk Type](crate::chunk_type::ChunkType) for more details.
+//!   <br /> See [Chunk Type](crate::chunk_type::ChunkType) for more details.
 //!
 //! * `Chunk Data`
-//! <br /> The data bytes appropriate to the chunk type, if any. This field can be of zero length.
+//!   <br /> The data bytes appropriate to the chunk type, if any. This field can be of zero length.
 //!
 //! * `CRC`
-//! <br /> A **4-byte** [CRC](https://www.wikiwand.com/en/Cyclic_redundancy_check) (Cyclic Redundancy Check) calculated on the preceding bytes in the chunk,
-//! including the chunk type code and chunk data fields, but not including the length field.
+//!   <br /> A **4-byte** [CRC](https://www.wikiwand.com/en/Cyclic_redundancy_check) (Cyclic Redundancy Check) calculated on the preceding bytes in the chunk,
+//!   including the chunk type code and chunk data fields, but not including the length field.
 
 use std::fmt::Display;
+use std::io::Write;
 use std::str::FromStr;
 
 use crate::chunk_type::ChunkType;
+use crate::codec::Codec;
 use crate::{checksum_32, u8_4_from_slice};
 use crate::{Error, Result, CHUNK_SIZE};
 
 use crc::CRC_32_ISO_HDLC;
 
+/// Longest keyword allowed in a `tEXt` chunk
+const MAX_KEYWORD_LEN: usize = 79;
+
+/// Number of data bytes shown by `Display`, enough for a quick look at a large chunk
+const DISPLAY_DATA_LEN: usize = 32;
+
 /// Chunk for a PNG file
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Chunk {
@@ -38,17 +46,60 @@ pub struct Chunk {
 }
 
 impl Chunk {
+    /// Builds a chunk, see [`Chunk::try_new`] for data of unknown size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the data is longer than the 4-byte length field can tell, over 4 GiB.
     pub fn new(chunk_type: ChunkType, chunk_data: Vec<u8>) -> Chunk {
-        let to_check = [&chunk_type.bytes(), chunk_data.as_slice()].concat();
+        Chunk::try_new(chunk_type, chunk_data).expect("chunk data too long for the length field")
+    }
 
-        let crc = checksum_32(&CRC_32_ISO_HDLC, &to_check);
+    /// Builds a chunk, or fails if the data is longer than the 4-byte length field can tell
+    pub fn try_new(chunk_type: ChunkType, chunk_data: Vec<u8>) -> Result<Chunk> {
+        let length = length_field(chunk_data.len())?;
+        let crc = crc_of(&chunk_type, &chunk_data);
 
-        Chunk {
-            length: chunk_data.len() as u32,
+        Ok(Chunk {
+            length,
             chunk_type,
             chunk_data,
             crc,
+        })
+    }
+
+    /// Same as `new` for callers holding borrowed data, the bytes are copied
+    pub fn from_slice(chunk_type: ChunkType, chunk_data: &[u8]) -> Chunk {
+        Chunk::new(chunk_type, chunk_data.to_vec())
+    }
+
+    /// Builds a standard `tEXt` chunk: `keyword`, a NUL separator, then `text`.
+    ///
+    /// The keyword must be 1 to 79 printable ASCII characters and the text must not contain
+    /// any NUL byte, otherwise readers would split the pair in the wrong place.
+    pub fn text(keyword: &str, text: &str) -> Result<Chunk> {
+        if keyword.is_empty()
+            || keyword.len() > MAX_KEYWORD_LEN
+            || !keyword.bytes().all(|b| b == b' ' || b.is_ascii_graphic())
+        {
+            return Err(Error::Custom(format!(
+                "Invalid tEXt keyword {:?}, it must be 1 to {} printable ASCII characters",
+                keyword, MAX_KEYWORD_LEN
+            )));
+        }
+        if text.contains('\0') {
+            return Err(Error::Custom(
+                "tEXt text must not contain NUL bytes".to_owned(),
+            ));
         }
+
+        let data = [keyword.as_bytes(), &[0], text.as_bytes()].concat();
+        Ok(Chunk::new(ChunkType::from_str("tEXt")?, data))
+    }
+
+    /// Starts a [`ChunkBuilder`] for fluent construction
+    pub fn builder() -> ChunkBuilder {
+        ChunkBuilder::default()
     }
 
     pub fn from_strings(chunk_type: &str, data: &str) -> Result<Chunk> {
@@ -68,47 +119,201 @@ impl Chunk {
         &self.chunk_type
     }
 
+    /// Returns true if the chunk type is defined by the PNG specification (or APNG),
+    /// see [`ChunkType::is_standard`]
+    pub fn is_standard(&self) -> bool {
+        self.chunk_type.is_standard()
+    }
+
     /// Return Main Data of chunk
     pub fn data(&self) -> &[u8] {
         &self.chunk_data
     }
 
+    /// Return Main Data of chunk for in-place editing
+    ///
+    /// The length and CRC are **stale** after editing until [`Chunk::refresh`] is called.
+    pub fn data_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.chunk_data
+    }
+
+    /// Recompute length and CRC after the data was edited through [`Chunk::data_mut`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if the data grew longer than the 4-byte length field can tell.
+    pub fn refresh(&mut self) {
+        self.length =
+            length_field(self.chunk_data.len()).expect("chunk data too long for the length field");
+        self.crc = self.computed_crc();
+    }
+
     /// Return CRC checksum
     pub fn crc(&self) -> u32 {
         self.crc
     }
 
+    /// Recompute the CRC from the current chunk type and data
+    pub fn computed_crc(&self) -> u32 {
+        crc_of(&self.chunk_type, &self.chunk_data)
+    }
+
+    /// Returns true if the CRC computed from the current content equals `expected`
+    pub fn crc_matches(&self, expected: u32) -> bool {
+        self.computed_crc() == expected
+    }
+
     /// Return the data of chunk as Result<string>
     pub fn data_as_string(&self) -> Result<String> {
         String::from_utf8(self.data().to_vec()).map_err(Error::from)
     }
 
+    /// Like `data_as_string`, without the trailing NULs and whitespace left by fixed-size padding
+    pub fn data_as_string_trimmed(&self) -> Result<String> {
+        let mut string = self.data_as_string()?;
+        let trimmed_len = string
+            .trim_end_matches(|c: char| c == '\0' || c.is_whitespace())
+            .len();
+        string.truncate(trimmed_len);
+        Ok(string)
+    }
+
+    /// Returns true if the data starts with the PNG signature, as when a whole PNG file is hidden
+    pub fn holds_png(&self) -> bool {
+        self.chunk_data.starts_with(&crate::Png::STANDARD_HEADER)
+    }
+
+    /// Number of characters in the data, or None if the data is not valid UTF-8.
+    ///
+    /// This differs from `length` as soon as the message has non-ASCII characters.
+    pub fn char_count(&self) -> Option<usize> {
+        self.data_as_string().ok().map(|s| s.chars().count())
+    }
+
+    /// Returns true if both chunks have the same type and data, ignoring the stored CRC.
+    ///
+    /// Unlike `==`, this tells a genuine content change apart from a corrupted CRC.
+    pub fn content_eq(&self, other: &Chunk) -> bool {
+        self.chunk_type == other.chunk_type && self.chunk_data == other.chunk_data
+    }
+
     /// Bytes representation for Chunk
     pub fn as_bytes(&self) -> Vec<u8> {
-        [
-            self.length.to_be_bytes().as_ref(),
-            &self.chunk_type.bytes(),
-            &self.chunk_data,
-            self.crc.to_be_bytes().as_ref(),
-        ]
-        .concat()
+        let mut bytes = Vec::with_capacity(self.chunk_data.len() + 3 * CHUNK_SIZE);
+        self.write_to(&mut bytes)
+            .expect("writing to a Vec never fails");
+        bytes
+    }
+
+    /// Writes the length, type, data and CRC to `writer` one after the other,
+    /// without copying the data into an intermediate buffer
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.length.to_be_bytes())?;
+        writer.write_all(&self.chunk_type.bytes())?;
+        writer.write_all(&self.chunk_data)?;
+        writer.write_all(&self.crc.to_be_bytes())?;
+        Ok(())
     }
 }
 
-impl TryFrom<&[u8]> for Chunk {
-    type Error = Error;
+/// Fluent constructor for [`Chunk`], applying codecs to the data before computing the CRC once
+#[derive(Clone, Debug, Default)]
+pub struct ChunkBuilder {
+    chunk_type: Option<ChunkType>,
+    data: Vec<u8>,
+    codecs: Vec<Codec>,
+}
+
+impl ChunkBuilder {
+    /// Sets the type of the chunk
+    pub fn chunk_type(mut self, chunk_type: ChunkType) -> Self {
+        self.chunk_type = Some(chunk_type);
+        self
+    }
+
+    /// Sets the data of the chunk, before any codec is applied
+    pub fn data<D: Into<Vec<u8>>>(mut self, data: D) -> Self {
+        self.data = data.into();
+        self
+    }
+
+    /// Adds a codec, codecs are applied in the order they were added
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.codecs.push(codec);
+        self
+    }
+
+    /// Applies the codecs and builds the chunk
+    pub fn build(self) -> Result<Chunk> {
+        let chunk_type = self
+            .chunk_type
+            .ok_or_else(|| Error::Custom("Chunk type is not set".to_owned()))?;
+
+        let data = self
+            .codecs
+            .iter()
+            .try_fold(self.data, |data, codec| codec.encode(&data))?;
+
+        Chunk::try_new(chunk_type, data)
+    }
+}
+
+/// Value of the 4-byte length field for `len` bytes of data, or an error if it doesn't fit
+fn length_field(len: usize) -> Result<u32> {
+    Ok(u32::try_from(len)?)
+}
+
+/// CRC over the chunk type and chunk data, as stored at the end of a chunk
+fn crc_of(chunk_type: &ChunkType, chunk_data: &[u8]) -> u32 {
+    checksum_32(
+        &CRC_32_ISO_HDLC,
+        &[&chunk_type.bytes(), chunk_data].concat(),
+    )
+}
+
+impl Chunk {
+    /// Parses a chunk like [`TryFrom`] does, but keeps the stored CRC even when it is wrong.
+    ///
+    /// Use [`Chunk::crc_matches`] on the result to find out whether the chunk is intact.
+    pub fn try_from_unchecked(bytes: &[u8]) -> Result<Chunk> {
+        Chunk::parse(bytes, false, true)
+    }
+
+    /// Whether `bytes` start with a whole chunk whose type code is made of ASCII letters,
+    /// whatever its CRC says
+    pub(crate) fn is_framed(bytes: &[u8]) -> bool {
+        if bytes.len() < 3 * CHUNK_SIZE {
+            return false;
+        }
 
-    fn try_from(bytes: &[u8]) -> Result<Self> {
         let length = u32::from_be_bytes(u8_4_from_slice(&bytes[0..CHUNK_SIZE]));
+        length as usize <= bytes.len() - 3 * CHUNK_SIZE
+            && bytes[CHUNK_SIZE..2 * CHUNK_SIZE]
+                .iter()
+                .all(u8::is_ascii_alphabetic)
+    }
+
+    /// Parses a chunk, optionally accepting a wrong CRC or a type code that is not
+    /// made of ASCII letters
+    pub(crate) fn parse(bytes: &[u8], check_crc: bool, check_type: bool) -> Result<Chunk> {
+        if bytes.len() < 3 * CHUNK_SIZE {
+            return Err(Error::Custom("Chunk is too short".to_owned()));
+        }
 
-        if bytes.len() != (length as usize + 3 * CHUNK_SIZE) as usize {
+        let length = u32::from_be_bytes(u8_4_from_slice(&bytes[0..CHUNK_SIZE]));
+
+        if bytes.len() != length as usize + 3 * CHUNK_SIZE {
             return Err(Error::Custom(
                 "Chunk contains incorrect length information".to_owned(),
             ));
         }
 
-        let chunk_type =
-            ChunkType::try_from(u8_4_from_slice(&bytes[CHUNK_SIZE..2 * CHUNK_SIZE])).unwrap();
+        let chunk_type = u8_4_from_slice(&bytes[CHUNK_SIZE..2 * CHUNK_SIZE]);
+        let chunk_type = if check_type {
+            ChunkType::try_from(chunk_type)?
+        } else {
+            ChunkType::from_bytes_unchecked(chunk_type)
+        };
 
         let chunk_data = bytes[2 * CHUNK_SIZE..bytes.len() - CHUNK_SIZE].to_vec();
 
@@ -116,9 +321,7 @@ impl TryFrom<&[u8]> for Chunk {
             &bytes[bytes.len() - CHUNK_SIZE..bytes.len()],
         ));
 
-        let to_check: Vec<u8> = [&chunk_type.bytes(), chunk_data.as_slice()].concat();
-
-        if checksum_32(&CRC_32_ISO_HDLC, &to_check) != crc {
+        if check_crc && crc_of(&chunk_type, &chunk_data) != crc {
             Err(Error::Custom("CRC checksum fails".to_owned()))
         } else {
             Ok(Chunk {
@@ -131,15 +334,32 @@ impl TryFrom<&[u8]> for Chunk {
     }
 }
 
+impl TryFrom<&[u8]> for Chunk {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Chunk::parse(bytes, true, true)
+    }
+}
+
 impl Display for Chunk {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let data = self.data();
+        let shown = &data[..data.len().min(DISPLAY_DATA_LEN)];
+        let elided = if shown.len() < data.len() {
+            format!(" … ({} bytes in total)", data.len())
+        } else {
+            String::new()
+        };
+
         write!(f, "Chunk\n{{\n")?;
         write!(
             f,
-            "\tlength: {}, chunk_type: {}\n\tdata: {:?}\n\tcrc: {}\n}}",
+            "\tlength: {}, chunk_type: {}\n\tdata: {:?}{}\n\tcrc: {}\n}}",
             self.length(),
             self.chunk_type(),
-            self.data(),
+            shown,
+            elided,
             self.crc()
         )
     }
@@ -223,6 +443,95 @@ mod tests {
         assert_eq!(chunk_string, expected_chunk_string);
     }
 
+    #[test]
+    fn test_chunk_string_trimmed() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::new(chunk_type, b" padded message \n\0\0\t\0".to_vec());
+        assert_eq!(chunk.data_as_string_trimmed().unwrap(), " padded message");
+        assert_eq!(
+            chunk.data_as_string().unwrap(),
+            " padded message \n\0\0\t\0"
+        );
+
+        let chunk = Chunk::new(chunk_type, vec![0xff, 0]);
+        assert!(chunk.data_as_string_trimmed().is_err());
+    }
+
+    #[test]
+    fn test_holds_png() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let png = crate::Png::from_chunks(vec![]).as_bytes();
+        assert!(Chunk::new(chunk_type, png.clone()).holds_png());
+        assert!(!Chunk::new(chunk_type, png[..7].to_vec()).holds_png());
+        assert!(!testing_chunk().holds_png());
+    }
+
+    #[test]
+    fn test_chunk_is_standard() {
+        let text = Chunk::text("Title", "pngchat").unwrap();
+        assert!(text.is_standard());
+        assert!(!testing_chunk().is_standard());
+    }
+
+    #[test]
+    fn test_chunk_try_new() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::try_new(chunk_type, b"message".to_vec()).unwrap();
+        assert_eq!(chunk, Chunk::new(chunk_type, b"message".to_vec()));
+    }
+
+    #[test]
+    fn test_length_field() {
+        assert_eq!(length_field(13).unwrap(), 13);
+        assert_eq!(length_field(u32::MAX as usize).unwrap(), u32::MAX);
+        assert!(matches!(
+            length_field(u32::MAX as usize + 1),
+            Err(Error::TryFromInt(_))
+        ));
+    }
+
+    #[test]
+    fn test_chunk_from_slice() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = b"borrowed data";
+        let chunk = Chunk::from_slice(chunk_type, data);
+        assert_eq!(chunk, Chunk::new(chunk_type, data.to_vec()));
+    }
+
+    #[test]
+    fn test_text_chunk() {
+        let chunk = Chunk::text("Comment", "hello").unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "tEXt");
+        assert_eq!(chunk.data(), b"Comment\0hello");
+
+        assert!(Chunk::text("Comment", "hel\0lo").is_err());
+        assert!(Chunk::text("", "hello").is_err());
+        assert!(Chunk::text(&"k".repeat(80), "hello").is_err());
+        assert!(Chunk::text("Com\0ment", "hello").is_err());
+    }
+
+    #[test]
+    fn test_zero_length_chunk() {
+        let chunk = Chunk::from_strings("ruSt", "").unwrap();
+        assert_eq!(chunk.length(), 0);
+        assert_eq!(chunk.crc(), checksum_32(&CRC_32_ISO_HDLC, b"ruSt"));
+        assert_eq!(chunk.data_as_string().unwrap(), "");
+
+        let parsed = Chunk::try_from(chunk.as_bytes().as_slice()).unwrap();
+        assert_eq!(parsed, chunk);
+    }
+
+    #[test]
+    fn test_chunk_char_count() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::from_slice(chunk_type, "hi 🦀".as_bytes());
+        assert_eq!(chunk.length(), 7);
+        assert_eq!(chunk.char_count(), Some(4));
+
+        let chunk = Chunk::new(chunk_type, vec![0xff, 0xfe]);
+        assert_eq!(chunk.char_count(), None);
+    }
+
     #[test]
     fn test_chunk_crc() {
         let chunk = testing_chunk();
@@ -277,6 +586,18 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_invalid_chunk_from_bytes_unchecked() {
+        let mut chunk_data = testing_chunk().as_bytes();
+        let last = chunk_data.len() - 1;
+        chunk_data[last] ^= 1;
+
+        let chunk = Chunk::try_from_unchecked(chunk_data.as_ref()).unwrap();
+
+        assert_eq!(chunk.crc(), 2882656334 ^ 1);
+        assert!(!chunk.crc_matches(chunk.crc()));
+    }
+
     #[test]
     fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
@@ -313,6 +634,86 @@ mod tests {
         println!("{}", chunk);
     }
 
+    #[test]
+    fn test_chunk_display_truncates_data() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::new(chunk_type, vec![7; 1000]);
+        let display = chunk.to_string();
+        assert!(display.contains(&format!("{:?} … (1000 bytes in total)", [7u8; 32])));
+
+        let chunk = Chunk::new(chunk_type, vec![7; 32]);
+        assert!(chunk
+            .to_string()
+            .contains(&format!("data: {:?}\n", [7u8; 32])));
+    }
+
+    #[test]
+    fn test_chunk_content_eq() {
+        let chunk = testing_chunk();
+        let mut corrupted = chunk.clone();
+        corrupted.crc = 0;
+
+        assert_ne!(chunk, corrupted);
+        assert!(chunk.content_eq(&corrupted));
+
+        let other = Chunk::from_strings("RuSt", "Another message").unwrap();
+        assert!(!chunk.content_eq(&other));
+    }
+
+    #[test]
+    fn test_chunk_computed_crc() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.computed_crc(), 2882656334);
+        assert!(chunk.crc_matches(chunk.crc()));
+        assert!(!chunk.crc_matches(2882656333));
+
+        let mut tampered = chunk.clone();
+        tampered.chunk_data[0] = b't';
+        assert!(!tampered.crc_matches(tampered.crc()));
+    }
+
+    #[test]
+    fn test_chunk_data_mut() {
+        let mut chunk = Chunk::from_strings("RuSt", "This is where").unwrap();
+        chunk
+            .data_mut()
+            .extend_from_slice(b" your secret message will be!");
+        assert_eq!(chunk.length(), 13);
+
+        chunk.refresh();
+        assert_eq!(chunk, testing_chunk());
+    }
+
+    #[test]
+    fn test_chunk_builder() {
+        let chunk = Chunk::builder()
+            .chunk_type(ChunkType::from_str("RuSt").unwrap())
+            .data("This is where your secret message will be!")
+            .build()
+            .unwrap();
+        assert_eq!(chunk, testing_chunk());
+
+        let chunk = Chunk::builder()
+            .chunk_type(ChunkType::from_str("RuSt").unwrap())
+            .data("secret")
+            .codec(Codec::Pad(16))
+            .build()
+            .unwrap();
+        assert_eq!(chunk.length(), 16);
+        assert_eq!(Codec::Pad(16).decode(chunk.data()).unwrap(), b"secret");
+
+        assert!(Chunk::builder().data("secret").build().is_err());
+    }
+
+    #[test]
+    fn test_chunk_write_to() {
+        let chunk = testing_chunk();
+        let mut written = Vec::new();
+        chunk.write_to(&mut written).unwrap();
+        assert_eq!(written, chunk.as_bytes());
+        assert_eq!(written.len(), chunk.length() as usize + 3 * CHUNK_SIZE);
+    }
+
     #[test]
     fn test_chunk_as_bytes() {
         let data_length: u32 = 42;
@@ -333,4 +734,38 @@ mod tests {
 
         assert_eq!(chunk.as_bytes(), chunk_data);
     }
+
+    mod proptests {
+        use super::*;
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+
+        fn arb_chunk() -> impl Strategy<Value = Chunk> {
+            ("[a-zA-Z]{4}", vec(any::<u8>(), 0..4096)).prop_map(|(chunk_type, data)| {
+                Chunk::new(ChunkType::from_str(&chunk_type).unwrap(), data)
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn chunk_round_trips_through_bytes(chunk in arb_chunk()) {
+                let bytes = chunk.as_bytes();
+                prop_assert_eq!(bytes.len(), chunk.length() as usize + 3 * CHUNK_SIZE);
+
+                let parsed = Chunk::try_from(bytes.as_slice()).unwrap();
+                prop_assert!(parsed.crc_matches(parsed.crc()));
+                prop_assert_eq!(parsed, chunk);
+            }
+
+            #[test]
+            fn corrupted_data_fails_crc_check(chunk in arb_chunk(), index: prop::sample::Index, flip in 1..=u8::MAX) {
+                prop_assume!(chunk.length() > 0);
+                let mut bytes = chunk.as_bytes();
+                bytes[2 * CHUNK_SIZE + index.index(chunk.length() as usize)] ^= flip;
+
+                prop_assert!(Chunk::try_from(bytes.as_slice()).is_err());
+                prop_assert!(Chunk::try_from_unchecked(&bytes).is_ok());
+            }
+        }
+    }
 }
@@ -9,8 +9,17 @@
 use std::fmt::Display;
 use std::str::{self, FromStr};
 
+use rand::Rng;
+
 use crate::{Error, Result, CHUNK_SIZE};
 
+/// Chunk types defined by the PNG specification and its APNG extension
+const STANDARD_TYPES: [&[u8; CHUNK_SIZE]; 21] = [
+    b"IHDR", b"PLTE", b"IDAT", b"IEND", b"tRNS", b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB",
+    b"tEXt", b"zTXt", b"iTXt", b"bKGD", b"hIST", b"pHYs", b"sPLT", b"tIME", b"acTL", b"fcTL",
+    b"fdAT",
+];
+
 /// A 4-byte chunk type code for PNG file
 #[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
 pub struct ChunkType(
@@ -19,11 +28,30 @@ pub struct ChunkType(
 );
 
 impl ChunkType {
+    /// Wraps raw type bytes without checking that they are ASCII letters,
+    /// so that unusual files can still be listed
+    pub(crate) fn from_bytes_unchecked(bytes: [u8; CHUNK_SIZE]) -> Self {
+        Self(bytes)
+    }
+
+    /// Generates a random ancillary, private and safe-to-copy type with a valid reserved bit,
+    /// the right properties for a custom message chunk
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let mut bytes: [u8; CHUNK_SIZE] = std::array::from_fn(|_| rng.gen_range(b'a'..=b'z'));
+        bytes[2].make_ascii_uppercase();
+        Self(bytes)
+    }
+
     /// Bytes representation for ChunkType
     pub fn bytes(&self) -> [u8; CHUNK_SIZE] {
         self.0
     }
 
+    /// Borrowed string representation, without allocating like `to_string` does
+    pub fn as_str(&self) -> Result<&str> {
+        str::from_utf8(&self.0).map_err(Error::from)
+    }
+
     /// The char at certian index of ChunkType's bytes
     fn at_char(&self, index: usize) -> char {
         (self.0)[index] as char
@@ -53,6 +81,17 @@ impl ChunkType {
     pub fn is_valid(&self) -> bool {
         !self.is_public() && self.is_reserved_bit_valid()
     }
+
+    /// Returns true if the chunk type is defined by the PNG specification (or APNG),
+    /// as opposed to a custom type such as a hidden message.
+    pub fn is_standard(&self) -> bool {
+        STANDARD_TYPES.contains(&&self.0)
+    }
+
+    /// Returns true if the chunk belongs to the APNG extension (`acTL`, `fcTL` or `fdAT`).
+    pub fn is_animation(&self) -> bool {
+        matches!(&self.0, b"acTL" | b"fcTL" | b"fdAT")
+    }
 }
 
 impl TryFrom<[u8; CHUNK_SIZE]> for ChunkType {
@@ -89,9 +128,10 @@ impl FromStr for ChunkType {
 }
 
 impl Display for ChunkType {
-    /// display the [`ChunkType`] using its string representation of bytes
+    /// display the [`ChunkType`] using its string representation of bytes,
+    /// escaping the non-printable ones of a type code that was not validated
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", str::from_utf8(&self.bytes()).map_err(Error::from)?)
+        write!(f, "{}", self.0.escape_ascii())
     }
 }
 
@@ -179,12 +219,58 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    pub fn test_chunk_type_is_animation() {
+        for ty in ["acTL", "fcTL", "fdAT"] {
+            assert!(ChunkType::from_str(ty).unwrap().is_animation());
+        }
+        assert!(!ChunkType::from_str("IDAT").unwrap().is_animation());
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_standard() {
+        assert!(ChunkType::from_str("IHDR").unwrap().is_standard());
+        assert!(ChunkType::from_str("tEXt").unwrap().is_standard());
+        assert!(!ChunkType::from_str("ruSt").unwrap().is_standard());
+    }
+
+    #[test]
+    pub fn test_chunk_type_random() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let chunk_type = ChunkType::random(&mut rng);
+            assert!(chunk_type.is_valid());
+            assert!(chunk_type.is_safe_to_copy());
+            assert!(!chunk_type.is_critical());
+            assert!(ChunkType::from_str(&chunk_type.to_string()).is_ok());
+        }
+    }
+
+    #[test]
+    pub fn test_chunk_type_random_seeded() {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha8Rng;
+
+        let generate = |seed| ChunkType::random(&mut ChaCha8Rng::seed_from_u64(seed));
+        assert_eq!(generate(42), generate(42));
+        assert!(generate(42).is_valid());
+        assert!((0..10)
+            .map(generate)
+            .any(|chunk_type| chunk_type != generate(42)));
+    }
+
     #[test]
     pub fn test_chunk_type_string() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
         assert_eq!(&chunk.to_string(), "RuSt");
     }
 
+    #[test]
+    pub fn test_chunk_type_as_str() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert_eq!(chunk.as_str().unwrap(), "RuSt");
+    }
+
     #[test]
     pub fn test_chunk_type_trait_impls() {
         let chunk_type_1: ChunkType = TryFrom::try_from([82, 117, 83, 116]).unwrap();
@@ -192,4 +278,54 @@ mod tests {
         let _chunk_string = format!("{}", chunk_type_1);
         let _are_chunks_equal = chunk_type_1 == chunk_type_2;
     }
+
+    /// Every combination of the four property bits, carried by the case of each letter:
+    /// uppercase first letter = critical, uppercase second = public,
+    /// uppercase third = valid reserved bit, lowercase fourth = safe to copy.
+    #[test]
+    pub fn test_chunk_type_property_matrix() {
+        // (type, critical, public, reserved bit valid, safe to copy)
+        let cases = [
+            ("rust", false, false, false, true),
+            ("rusT", false, false, false, false),
+            ("ruSt", false, false, true, true),
+            ("ruST", false, false, true, false),
+            ("rUst", false, true, false, true),
+            ("rUsT", false, true, false, false),
+            ("rUSt", false, true, true, true),
+            ("rUST", false, true, true, false),
+            ("Rust", true, false, false, true),
+            ("RusT", true, false, false, false),
+            ("RuSt", true, false, true, true),
+            ("RuST", true, false, true, false),
+            ("RUst", true, true, false, true),
+            ("RUsT", true, true, false, false),
+            ("RUSt", true, true, true, true),
+            ("RUST", true, true, true, false),
+        ];
+
+        for (code, critical, public, reserved_valid, safe_to_copy) in cases {
+            let chunk_type = ChunkType::from_str(code).unwrap();
+            assert_eq!(chunk_type.is_critical(), critical, "{} critical", code);
+            assert_eq!(chunk_type.is_public(), public, "{} public", code);
+            assert_eq!(
+                chunk_type.is_reserved_bit_valid(),
+                reserved_valid,
+                "{} reserved bit",
+                code
+            );
+            assert_eq!(
+                chunk_type.is_safe_to_copy(),
+                safe_to_copy,
+                "{} safe to copy",
+                code
+            );
+            assert_eq!(
+                chunk_type.is_valid(),
+                !public && reserved_valid,
+                "{} valid",
+                code
+            );
+        }
+    }
 }
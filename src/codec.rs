@@ -0,0 +1,288 @@
+//! Transformations applied to a message before it is stored in a chunk
+//!
+//! Each codec has an encoding function used by `encode` and a matching decoding function used by `decode`.
+
+use std::io::{Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{u8_4_from_slice, Error, Result, CHUNK_SIZE};
+
+/// Marker starting data protected by [`sign`]
+const HMAC_MARKER: &[u8; CHUNK_SIZE] = b"HMAC";
+/// Size of an HMAC-SHA256 tag
+const HMAC_SIZE: usize = 32;
+/// Marker starting data produced by [`compress`]
+const DEFLATE_MARKER: &[u8; CHUNK_SIZE] = b"ZLIB";
+/// Marker starting data produced by [`store`], which is not compressed
+const STORED_MARKER: &[u8; CHUNK_SIZE] = b"RAW\0";
+
+/// A reversible transformation of message bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Codec {
+    /// Length-prefix and zero-pad the message to the given size, see [`pad`].
+    /// The size is only used when encoding.
+    Pad(usize),
+    /// Prefix the message with an HMAC-SHA256 tag computed with the given key, see [`sign`]
+    Hmac(Vec<u8>),
+    /// Compress the message with zlib, see [`compress`]
+    Deflate,
+}
+
+impl Codec {
+    /// Applies the codec to a message before it is stored
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Codec::Pad(size) => pad(data, *size),
+            Codec::Hmac(key) => sign(data, key),
+            Codec::Deflate => compress(data),
+        }
+    }
+
+    /// Reverts [`Codec::encode`] on stored chunk data
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Codec::Pad(_) => unpad(data),
+            Codec::Hmac(key) => verify(data, key),
+            Codec::Deflate => decompress(data),
+        }
+    }
+}
+
+/// Prefixes the message with its 4-byte big-endian length and pads it with zeros up to `size` bytes,
+/// so the chunk length no longer reveals the message length.
+pub fn pad(message: &[u8], size: usize) -> Result<Vec<u8>> {
+    if message.len() + CHUNK_SIZE > size {
+        return Err(Error::Custom(format!(
+            "Message of {} bytes does not fit in {} padded bytes",
+            message.len(),
+            size
+        )));
+    }
+
+    let mut data = u32::try_from(message.len())?.to_be_bytes().to_vec();
+    data.extend_from_slice(message);
+    data.resize(size, 0);
+    Ok(data)
+}
+
+/// Recovers the original message from data produced by [`pad`]
+pub fn unpad(data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < CHUNK_SIZE {
+        return Err(Error::Custom(
+            "Padded data misses its length prefix".to_owned(),
+        ));
+    }
+
+    let length = u32::from_be_bytes(u8_4_from_slice(&data[..CHUNK_SIZE])) as usize;
+    data[CHUNK_SIZE..]
+        .get(..length)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| Error::Custom("Padded data is shorter than its length prefix".to_owned()))
+}
+
+fn hmac_sha256(key: &[u8]) -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length")
+}
+
+/// Prefixes the message with the `HMAC` marker and its HMAC-SHA256 tag,
+/// so that intentional edits can be detected, which the chunk CRC can't guarantee.
+pub fn sign(message: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    let mut mac = hmac_sha256(key);
+    mac.update(message);
+
+    Ok([HMAC_MARKER.as_ref(), &mac.finalize().into_bytes(), message].concat())
+}
+
+/// Checks data produced by [`sign`] and returns the message only if the tag matches
+pub fn verify(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < CHUNK_SIZE + HMAC_SIZE || &data[..CHUNK_SIZE] != HMAC_MARKER {
+        return Err(Error::Custom("Message does not carry an HMAC".to_owned()));
+    }
+
+    let (tag, message) = data[CHUNK_SIZE..].split_at(HMAC_SIZE);
+    let mut mac = hmac_sha256(key);
+    mac.update(message);
+    mac.verify_slice(tag).map_err(|_| {
+        Error::Custom("HMAC mismatch, the message was altered or the key is wrong".to_owned())
+    })?;
+
+    Ok(message.to_vec())
+}
+
+/// Compresses the message with zlib behind the `ZLIB` marker
+pub fn compress(message: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(DEFLATE_MARKER.to_vec(), Compression::best());
+    encoder.write_all(message)?;
+    Ok(encoder.finish()?)
+}
+
+/// Leaves the message uncompressed in a form [`decompress`] reads back as it is.
+///
+/// Only a message starting with a marker needs the `RAW\0` marker in front of it,
+/// any other message is kept unchanged.
+pub fn store(message: &[u8]) -> Vec<u8> {
+    if message.starts_with(DEFLATE_MARKER) || message.starts_with(STORED_MARKER) {
+        [STORED_MARKER.as_ref(), message].concat()
+    } else {
+        message.to_vec()
+    }
+}
+
+/// Recovers the message from data produced by [`compress`] or [`store`].
+/// Data without a marker was left uncompressed and is returned as it is.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    if let Some(stored) = data.strip_prefix(STORED_MARKER.as_ref()) {
+        return Ok(stored.to_vec());
+    }
+    match data.strip_prefix(DEFLATE_MARKER.as_ref()) {
+        Some(compressed) => {
+            let mut message = Vec::new();
+            ZlibDecoder::new(compressed).read_to_end(&mut message)?;
+            Ok(message)
+        }
+        None => Ok(data.to_vec()),
+    }
+}
+
+/// Splits `payload` into `count` pieces, each prefixed with its 4-byte index and the 4-byte shard count
+pub fn shard(payload: &[u8], count: usize) -> Result<Vec<Vec<u8>>> {
+    if count == 0 {
+        return Err(Error::Custom("At least one shard is required".to_owned()));
+    }
+
+    let count_field = u32::try_from(count)?.to_be_bytes();
+    let size = payload.len().div_ceil(count);
+    Ok((0..count)
+        .map(|index| {
+            let start = (index * size).min(payload.len());
+            let end = (start + size).min(payload.len());
+            [
+                (index as u32).to_be_bytes().as_ref(),
+                count_field.as_ref(),
+                &payload[start..end],
+            ]
+            .concat()
+        })
+        .collect())
+}
+
+/// Reassembles a payload from shards produced by [`shard`], given in any order
+pub fn unshard(shards: &[&[u8]]) -> Result<Vec<u8>> {
+    let mut pieces: Vec<(usize, usize, &[u8])> = Vec::with_capacity(shards.len());
+    for shard in shards {
+        if shard.len() < 2 * CHUNK_SIZE {
+            return Err(Error::Custom("Shard misses its sequence header".to_owned()));
+        }
+        let index = u32::from_be_bytes(u8_4_from_slice(&shard[..CHUNK_SIZE])) as usize;
+        let count = u32::from_be_bytes(u8_4_from_slice(&shard[CHUNK_SIZE..2 * CHUNK_SIZE]));
+        pieces.push((index, count as usize, &shard[2 * CHUNK_SIZE..]));
+    }
+    pieces.sort_by_key(|&(index, _, _)| index);
+
+    let count = pieces.first().map_or(0, |&(_, count, _)| count);
+    if pieces.iter().any(|&(_, c, _)| c != count) {
+        return Err(Error::Custom(
+            "Shards belong to different payloads".to_owned(),
+        ));
+    }
+
+    let mut payload = Vec::new();
+    for expected in 0..count {
+        match pieces.get(expected) {
+            Some(&(index, _, data)) if index == expected => payload.extend_from_slice(data),
+            _ => {
+                return Err(Error::Custom(format!(
+                    "Missing shard {} of {}",
+                    expected + 1,
+                    count
+                )))
+            }
+        }
+    }
+    if pieces.len() != count {
+        return Err(Error::Custom("Duplicate shards found".to_owned()));
+    }
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad_round_trip() {
+        let padded = pad(b"secret", 32).unwrap();
+        assert_eq!(padded.len(), 32);
+        assert_eq!(unpad(&padded).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn test_pad_too_small() {
+        assert!(pad(b"secret", 8).is_err());
+    }
+
+    #[test]
+    fn test_hmac_round_trip() {
+        let signed = sign(b"secret", b"key").unwrap();
+        assert_eq!(verify(&signed, b"key").unwrap(), b"secret");
+        assert!(verify(&signed, b"other key").is_err());
+
+        let mut tampered = signed.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(verify(&tampered, b"key").is_err());
+        assert!(verify(b"secret", b"key").is_err());
+    }
+
+    #[test]
+    fn test_compress_round_trip() {
+        let message = b"a message repeated, a message repeated, a message repeated";
+        let compressed = compress(message).unwrap();
+        assert!(compressed.len() < message.len());
+        assert_eq!(decompress(&compressed).unwrap(), message);
+
+        assert_eq!(decompress(b"left as it is").unwrap(), b"left as it is");
+        assert!(decompress(b"ZLIBnot zlib data").is_err());
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        for message in [&b"ZLIB ok"[..], b"RAW\0 too", b"plain"] {
+            assert_eq!(decompress(&store(message)).unwrap(), message);
+        }
+        assert_eq!(store(b"plain"), b"plain");
+        assert_eq!(store(b"ZLIB ok"), b"RAW\0ZLIB ok");
+    }
+
+    #[test]
+    fn test_shard_round_trip() {
+        let payload = b"a payload spread over several images";
+        let shards = shard(payload, 3).unwrap();
+        assert_eq!(shards.len(), 3);
+
+        let mut refs: Vec<&[u8]> = shards.iter().map(Vec::as_slice).collect();
+        refs.reverse();
+        assert_eq!(unshard(&refs).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_unshard_missing() {
+        let shards = shard(b"a payload", 3).unwrap();
+        let refs: Vec<&[u8]> = vec![&shards[0], &shards[2]];
+
+        let err = unshard(&refs).unwrap_err();
+        assert_eq!(err.to_string(), "Missing shard 2 of 3");
+    }
+
+    #[test]
+    fn test_unpad_invalid() {
+        assert!(unpad(&[0, 0]).is_err());
+        assert!(unpad(&[0, 0, 0, 9, 1, 2]).is_err());
+    }
+}
@@ -1,81 +1,1365 @@
 //! Functions for command line usage
 
-use crate::args::{DecodeArgs, EncodeArgs, PrintArgs, RemoveArgs};
+use std::cmp::Reverse;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Cursor, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::{self, FromStr};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use notify::{RecursiveMode, Watcher};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use regex::Regex;
+
+use crate::args::{
+    ColorMode, CompressMode, CopyChunkArgs, DecodeArgs, DedupArgs, EncodeArgs, ExportArgs, GetArgs,
+    HasArgs, InfoArgs, LintArgs, MessageEncoding, MoveArgs, NormalizeArgs, OnExists, PrintArgs,
+    RawChunksArgs, RemoveArgs, ShardArgs, SortBy, UnshardArgs, ValidateTypeArgs, WatchArgs,
+};
+use crate::chunk_type::ChunkType;
+use crate::codec::{self, Codec};
+use crate::encoder::{Decoder, Encoder};
 use crate::png::{Chunk, Png};
-use crate::{Error, Result};
+use crate::reader::ChunkReader;
+use crate::{from_hex, to_hex, Error, Result};
+
+/// Loads a PNG file, through a memory mapping when the `mmap` feature is enabled
+fn read_png<P: AsRef<Path>>(path: P) -> Result<Png> {
+    #[cfg(feature = "mmap")]
+    return Png::from_file_mmap(path);
+
+    #[cfg(not(feature = "mmap"))]
+    Png::from_file(path)
+}
+
+/// Loads a PNG file like `read_png`, but keeps chunks with a wrong CRC if `check_crc` is false.
+/// The path may also be an HTTP(S) URL.
+fn read_png_checked<P: AsRef<Path>>(path: P, check_crc: bool) -> Result<Png> {
+    if is_url(path.as_ref()) {
+        let bytes = fetch(path.as_ref())?;
+        if check_crc {
+            Png::from_bytes(&bytes)
+        } else {
+            Png::from_bytes_unchecked(&bytes)
+        }
+    } else if check_crc {
+        read_png(path)
+    } else {
+        Png::from_file_unchecked(path)
+    }
+}
+
+/// Whether a file path argument is an HTTP(S) URL
+fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+/// Downloads a file given by URL, which needs the `net` feature
+#[cfg(feature = "net")]
+fn fetch(url: &Path) -> Result<Vec<u8>> {
+    crate::net::fetch(&url.to_string_lossy())
+}
+
+#[cfg(not(feature = "net"))]
+fn fetch(url: &Path) -> Result<Vec<u8>> {
+    Err(Error::Custom(format!(
+        "Cannot download {}, pngchat was built without the net feature",
+        url.display()
+    )))
+}
 
 /// Encodes a message into a PNG file and saves the result
 pub fn encode(args: &EncodeArgs) -> Result<()> {
-    let mut png = Png::from_file(&args.file_path)?;
-    let chunk = Chunk::from_strings(&args.chunk_type, &args.message)?;
-    png.append_chunk(chunk);
+    let (args, chunk_type) = resolve_chunk_type(args)?;
+    let args = &args;
+
+    let mut png = read_png(&args.file_path)?;
+    let chunk = match &args.text {
+        Some(keyword) => text_chunk(args, &chunk_type, keyword)?,
+        None => {
+            let mut message = message_bytes(args)?;
+            if let Some(mode) = args.compress {
+                message = compress_message(message, mode)?;
+            }
+            encoder(args, ChunkType::from_str(&chunk_type)?).chunk(&message)?
+        }
+    };
 
-    if let Some(output_file) = &args.output_file {
-        png.wrtie_file(output_file)
+    if args.dedup_check || args.if_absent {
+        if let Some(i) = find_duplicate(&png, &chunk) {
+            eprintln!(
+                "warning: the same data is already stored in chunk#{} ({})",
+                i,
+                png.chunks()[i].chunk_type()
+            );
+            if args.if_absent {
+                if !args.quiet {
+                    println!(
+                        "Nothing encoded, {} is left untouched",
+                        args.file_path.display()
+                    );
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    let encoded_len = chunk.length();
+    let chunk_type = chunk.chunk_type().to_string();
+    let replace = match on_exists(args) {
+        _ if !png.contains_chunk_type(&chunk_type) => false,
+        OnExists::Append => false,
+        OnExists::Replace => true,
+        OnExists::Error => {
+            return Err(Error::Custom(format!(
+                "{} already has a chunk of type {}, pick another --on-exists policy to go on",
+                args.file_path.display(),
+                chunk_type
+            )))
+        }
+        OnExists::Skip => {
+            if !args.quiet {
+                println!(
+                    "Nothing encoded, {} already has a chunk of type {}",
+                    args.file_path.display(),
+                    chunk_type
+                );
+            }
+            return Ok(());
+        }
+    };
+
+    if replace {
+        png.upsert_chunk(chunk);
+    } else if args.prepend {
+        png.prepend_chunk(chunk);
     } else {
-        png.wrtie_file(&args.file_path)
+        let index = if args.apng_safe {
+            png.apng_safe_index()
+        } else {
+            png.len()
+        };
+        if png.splits_image_data(index) {
+            eprintln!(
+                "warning: the message chunk is placed inside the image data and may break it"
+            );
+        }
+        png.insert_chunk(index, chunk)?;
+    }
+
+    let output_file = match (&args.output_file, &args.output_dir) {
+        (Some(output_file), _) => Some(output_file.clone()),
+        (None, Some(output_dir)) => Some(path_in_dir(output_dir, &args.file_path)?),
+        (None, None) => None,
+    };
+
+    let output_file = match output_file {
+        Some(output_file) => {
+            if !args.overwrite && same_file(&args.file_path, &output_file)? {
+                return Err(Error::Custom(format!(
+                    "Output file {} is the input file, pass --overwrite to modify it in place",
+                    output_file.display()
+                )));
+            }
+            output_file
+        }
+        None => args.file_path.clone(),
+    };
+    let in_place = same_file(&args.file_path, &output_file)?;
+    write_png(&png, &output_file, args.preserve_mtime && in_place)?;
+    log_operation(args.log.as_deref(), "encode", &output_file, &chunk_type);
+
+    if !args.quiet {
+        println!(
+            "Encoded {} bytes into '{}' chunk; wrote {} ({} bytes)",
+            encoded_len,
+            chunk_type,
+            output_file.display(),
+            fs::metadata(&output_file)?.len()
+        );
+    }
+    Ok(())
+}
+
+/// The `--on-exists` policy, taking the deprecated `--upsert` into account
+fn on_exists(args: &EncodeArgs) -> OnExists {
+    if args.upsert {
+        eprintln!("warning: --upsert is deprecated, use --on-exists=replace");
+        return OnExists::Replace;
+    }
+    args.on_exists
+}
+
+/// Position of the first chunk of any type holding the same data as `chunk`.
+/// Empty data is never reported, it is what every `IEND` holds.
+fn find_duplicate(png: &Png, chunk: &Chunk) -> Option<usize> {
+    if chunk.data().is_empty() {
+        return None;
     }
+    png.chunks()
+        .iter()
+        .position(|existing| existing.data() == chunk.data())
+}
+
+/// Path of a file named like `file_path` inside `dir`, creating `dir` if needed
+fn path_in_dir(dir: &Path, file_path: &Path) -> Result<PathBuf> {
+    let file_name = file_path
+        .file_name()
+        .ok_or_else(|| Error::Custom(format!("{} is not a file path", file_path.display())))?;
+    fs::create_dir_all(dir)?;
+    Ok(dir.join(file_name))
+}
+
+/// Compresses the message for `encode --compress` and reports how much it saved
+fn compress_message(message: Vec<u8>, mode: CompressMode) -> Result<Vec<u8>> {
+    let compressed = Codec::Deflate.encode(&message)?;
+    eprintln!(
+        "Compressed the message from {} to {} bytes ({:.0}%)",
+        message.len(),
+        compressed.len(),
+        compressed.len() as f64 * 100.0 / message.len().max(1) as f64
+    );
+
+    if compressed.len() < message.len() {
+        return Ok(compressed);
+    }
+    match mode {
+        CompressMode::Auto => {
+            eprintln!("Compression does not help, the message is stored uncompressed");
+            Ok(codec::store(&message))
+        }
+        CompressMode::Always => {
+            eprintln!("warning: compression made the message larger, try --compress=auto");
+            Ok(compressed)
+        }
+    }
+}
+
+/// The `Encoder` applying the codecs asked for on the command line.
+/// Compression is left out, `--compress=auto` needs to compare sizes first.
+fn encoder(args: &EncodeArgs, chunk_type: ChunkType) -> Encoder {
+    let mut encoder = Encoder::new(chunk_type);
+    // padding comes last so that it also hides the size of the HMAC
+    if let Some(key) = &args.hmac {
+        encoder = encoder.codec(Codec::Hmac(key.as_bytes().to_vec()));
+    }
+    if let Some(size) = args.pad_to {
+        encoder = encoder.codec(Codec::Pad(size));
+    }
+    encoder
+}
+
+/// The chunk type to encode with, generated and printed for `--random-type`.
+///
+/// Without a chunk type argument, clap fills the chunk type and message positions with the
+/// message and output file, so they are shifted back in place.
+fn resolve_chunk_type(args: &EncodeArgs) -> Result<(EncodeArgs, String)> {
+    let mut args = args.clone();
+    if !args.random_type {
+        let chunk_type = args.chunk_type.clone().unwrap_or_default();
+        return Ok((args, chunk_type));
+    }
+    if args.output_file.is_some() {
+        return Err(Error::Custom(
+            "Too many arguments, --random-type replaces the chunk type argument".to_owned(),
+        ));
+    }
+
+    args.output_file = args.message.take().map(PathBuf::from);
+    args.message = args.chunk_type.take();
+    let chunk_type = match args.seed {
+        // ChaCha, unlike the standard RNG, gives the same values across rand versions
+        Some(seed) => ChunkType::random(&mut ChaCha8Rng::seed_from_u64(seed)),
+        None => ChunkType::random(&mut rand::thread_rng()),
+    }
+    .to_string();
+    println!("chunk type: {}", chunk_type);
+    Ok((args, chunk_type))
+}
+
+/// Builds the standard `tEXt` chunk requested with `encode --text`
+fn text_chunk(args: &EncodeArgs, chunk_type: &str, keyword: &str) -> Result<Chunk> {
+    if chunk_type != "tEXt" {
+        return Err(Error::Custom(
+            "--text writes standard metadata, the chunk type must be tEXt".to_owned(),
+        ));
+    }
+    let text = String::from_utf8(message_bytes(args)?)?;
+    Chunk::text(keyword, &text)
+}
+
+/// The message given as argument, or the whole of stdin when it is omitted or `-`,
+/// turned into bytes with `--encoding`
+fn message_bytes(args: &EncodeArgs) -> Result<Vec<u8>> {
+    let message = raw_message(args)?;
+    let message = match args.encoding {
+        MessageEncoding::Utf8 => message,
+        encoding => encode_text(&String::from_utf8(message)?, encoding)?,
+    };
+    if args.ascii_only {
+        check_ascii(&message)?;
+    }
+    Ok(message)
+}
+
+/// Fails on the first byte of `message` that is not ASCII, for `--ascii-only`
+fn check_ascii(message: &[u8]) -> Result<()> {
+    match message.iter().position(|b| !b.is_ascii()) {
+        Some(i) => Err(Error::Custom(format!(
+            "The message has the non-ASCII byte {:#04x} at position {}, refused by --ascii-only",
+            message[i], i
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn raw_message(args: &EncodeArgs) -> Result<Vec<u8>> {
+    match args.message.as_deref() {
+        Some("-") | None => {
+            if io::stdin().is_terminal() {
+                return Err(Error::Custom(
+                    "No message given, pass it as argument or pipe it through stdin".to_owned(),
+                ));
+            }
+            let mut message = Vec::new();
+            io::stdin().read_to_end(&mut message)?;
+            Ok(message)
+        }
+        Some(message) => Ok(message.as_bytes().to_vec()),
+    }
+}
+
+/// Bytes stored for `text` with the given encoding
+fn encode_text(text: &str, encoding: MessageEncoding) -> Result<Vec<u8>> {
+    match encoding {
+        MessageEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+        MessageEncoding::Latin1 => text
+            .chars()
+            .map(|c| {
+                u8::try_from(c).map_err(|_| {
+                    Error::Custom(format!(
+                        "'{}' can't be encoded as latin-1",
+                        c.escape_debug()
+                    ))
+                })
+            })
+            .collect(),
+        MessageEncoding::Hex => from_hex(text),
+    }
+}
+
+/// Text of the stored `bytes` with the given encoding, the reverse of `encode_text`
+fn decode_text(bytes: Vec<u8>, encoding: MessageEncoding) -> Result<String> {
+    match encoding {
+        MessageEncoding::Utf8 => Ok(String::from_utf8(bytes)?),
+        MessageEncoding::Latin1 => Ok(bytes.into_iter().map(char::from).collect()),
+        MessageEncoding::Hex => Ok(to_hex(&bytes)),
+    }
+}
+
+/// Writes `png` to `path`, keeping the modification time of the replaced file if `preserve_mtime`
+fn write_png(png: &Png, path: &Path, preserve_mtime: bool) -> Result<()> {
+    if preserve_mtime {
+        png.write_file_keeping_mtime(path)
+    } else {
+        png.write_file(path)
+    }
+}
+
+/// Returns true if both paths point to the same existing file
+fn same_file(a: &Path, b: &Path) -> Result<bool> {
+    if !b.exists() {
+        return Ok(false);
+    }
+    Ok(fs::canonicalize(a)? == fs::canonicalize(b)?)
 }
 
 /// Searches for a message hidden in a PNG file and prints the message if one is found
 pub fn decode(args: &DecodeArgs) -> Result<()> {
-    let png = Png::from_file(&args.file_path)?;
+    let png = read_png_checked(&args.file_path, !args.no_crc_check)?;
+    let chunk_types = match &args.types_file {
+        Some(types_file) => read_types_file(types_file)?,
+        None => args.chunk_type.iter().cloned().collect(),
+    };
+
+    if args.count_only {
+        return count_messages(&png, &chunk_types, args);
+    }
+
+    let clipboard = to_clipboard(args);
+    let mut copied = Vec::new();
+    let mut found = false;
+    for chunk_type in &chunk_types {
+        let results = png.decode(chunk_type)?;
+        if results.is_empty() {
+            continue;
+        }
+        found = true;
 
-    if let Some(chunk) = png.chunk_by_type(&args.chunk_type) {
-        println!("msg: {}", chunk.data_as_string()?);
-        Ok(())
+        let shown = if args.all { results.len() } else { 1 };
+        for result in &results[..shown] {
+            if args.nested && result.data.starts_with(&Png::STANDARD_HEADER) {
+                println!("msg: nested PNG ({} bytes)", result.data.len());
+                let mut lines = Vec::new();
+                describe_nested(&result.data, 1, args, &mut lines);
+                lines.iter().for_each(|line| println!("{}", line));
+                continue;
+            }
+            let message = decode_message(result.chunk_type, &result.data, args)?;
+            if clipboard {
+                copied.push(message);
+            } else if args.types_file.is_some() {
+                println!("msg ({}): {}", chunk_type, message);
+            } else {
+                println!("msg: {}", message);
+            }
+        }
+
+        if args.count {
+            println!(
+                "found {} message{} under type {}",
+                results.len(),
+                if results.len() == 1 { "" } else { "s" },
+                chunk_type
+            );
+        }
+    }
+
+    if !found {
+        return Err(Error::ChunkNotFound(chunk_types.join(", ")));
+    }
+
+    #[cfg(feature = "clipboard")]
+    if clipboard {
+        copy_to_clipboard(copied.join("\n"))?;
+        eprintln!("Copied {} message(s) to the clipboard", copied.len());
+    }
+
+    Ok(())
+}
+
+/// Prints how many chunks of each type exist for `decode --count-only`, without decoding them
+fn count_messages(png: &Png, chunk_types: &[String], args: &DecodeArgs) -> Result<()> {
+    let mut total = 0;
+    for chunk_type in chunk_types {
+        let count = png.iter_by_type(chunk_type).count();
+        total += count;
+        if args.types_file.is_some() {
+            println!("{}: {}", chunk_type, count);
+        } else {
+            println!("{}", count);
+        }
+    }
+
+    if total == 0 {
+        return Err(Error::ChunkNotFound(chunk_types.join(", ")));
+    }
+    Ok(())
+}
+
+/// Whether decoded messages go to the clipboard instead of stdout
+#[cfg(feature = "clipboard")]
+fn to_clipboard(args: &DecodeArgs) -> bool {
+    args.clipboard
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn to_clipboard(_: &DecodeArgs) -> bool {
+    false
+}
+
+#[cfg(feature = "clipboard")]
+fn copy_to_clipboard(text: String) -> Result<()> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(|e| Error::Custom(format!("Could not copy to the clipboard: {}", e)))
+}
+
+/// Reads newline-delimited chunk types, ignoring blank lines
+fn read_types_file(path: &Path) -> Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect())
+}
+
+/// Deepest level of PNG files hidden inside each other that `decode --nested` expands
+const MAX_NESTING_DEPTH: usize = 8;
+
+/// Describes the chunks of the PNG file in `data`, one line each indented by nesting level.
+///
+/// Custom chunks are decoded like top level messages, and PNG files nested further down are
+/// expanded up to `MAX_NESTING_DEPTH`.
+fn describe_nested(data: &[u8], depth: usize, args: &DecodeArgs, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    let png = match Png::from_bytes(data) {
+        Ok(png) => png,
+        Err(e) => {
+            lines.push(format!("{}[level {}] invalid PNG: {}", indent, depth, e));
+            return;
+        }
+    };
+
+    for chunk in png.chunks() {
+        let prefix = format!("{}[level {}] {}", indent, depth, chunk.chunk_type());
+        if chunk.holds_png() && depth < MAX_NESTING_DEPTH {
+            lines.push(format!("{}: nested PNG ({} bytes)", prefix, chunk.length()));
+            describe_nested(chunk.data(), depth + 1, args, lines);
+        } else if chunk.holds_png() {
+            lines.push(format!(
+                "{}: nested PNG, not expanded past level {}",
+                prefix, MAX_NESTING_DEPTH
+            ));
+        } else if chunk.is_standard() {
+            lines.push(format!("{} ({} bytes)", prefix, chunk.length()));
+        } else {
+            let message = decode_message(*chunk.chunk_type(), chunk.data(), args)
+                .unwrap_or_else(|_| to_hex(chunk.data()));
+            lines.push(format!("{}: {}", prefix, message));
+        }
+    }
+}
+
+/// The `Decoder` reverting the codecs given on the command line, in the order `encode` applies them
+fn decoder(args: &DecodeArgs, chunk_type: ChunkType) -> Decoder {
+    let mut decoder = Decoder::new(chunk_type);
+    if args.compressed {
+        decoder = decoder.codec(Codec::Deflate);
+    }
+    if let Some(key) = &args.verify_hmac {
+        decoder = decoder.codec(Codec::Hmac(key.as_bytes().to_vec()));
+    }
+    if args.padded {
+        decoder = decoder.codec(Codec::Pad(0));
+    }
+    decoder
+}
+
+/// Turns the data of a chunk back into the message, as text or as hex
+fn decode_message(chunk_type: ChunkType, data: &[u8], args: &DecodeArgs) -> Result<String> {
+    let bytes = decoder(args, chunk_type).decode_data(data)?;
+
+    if args.hex {
+        Ok(to_hex(&bytes))
     } else {
-        // Err(Error::Custom(&msg))
-        Err(Error::Custom(format!(
-            "This file does not contain msg of chunk type {}",
-            args.chunk_type
-        )))
+        decode_text(bytes, args.encoding)
     }
 }
 
-/// Removes a chunk from a PNG file and saves the result
+/// Removes a chunk from each PNG file and saves the result
 pub fn remove(args: &RemoveArgs) -> Result<()> {
-    let mut png = Png::from_file(&args.file_path)?;
-    png.remove_chunk(&args.chunk_type)?;
-    png.wrtie_file(&args.file_path)
+    for_each_file(
+        &args.file_paths,
+        args.keep_going,
+        args.jobs,
+        |file_path, _| remove_from_file(file_path, args),
+    )
+}
+
+fn remove_from_file(file_path: &Path, args: &RemoveArgs) -> Result<()> {
+    let mut png = read_png(file_path)?;
+    if args.before_iend_only {
+        remove_before_iend(&mut png, file_path, &args.chunk_type)?;
+    } else {
+        png.remove_chunk(&args.chunk_type)?;
+    }
+
+    if !args.yes && io::stdout().is_terminal() {
+        let question = format!(
+            "Remove chunk '{}' from {} and overwrite?",
+            args.chunk_type,
+            file_path.display()
+        );
+        if !confirm(&question)? {
+            return Err(Error::Custom(
+                "Aborted, the file was left untouched".to_owned(),
+            ));
+        }
+    }
+
+    write_png(&png, file_path, args.preserve_mtime)?;
+    log_operation(args.log.as_deref(), "remove", file_path, &args.chunk_type);
+    Ok(())
+}
+
+/// Removes the first chunk of the type placed before `IEND` for `remove --before-iend-only`,
+/// reporting the ones after `IEND` that are left in place
+fn remove_before_iend(png: &mut Png, file_path: &Path, chunk_type: &str) -> Result<()> {
+    let iend = png.apng_safe_index();
+    let (before, after): (Vec<usize>, Vec<usize>) = png
+        .positions_by_type(chunk_type)
+        .into_iter()
+        .partition(|&i| i < iend);
+
+    if !after.is_empty() {
+        eprintln!(
+            "{}: left {} chunk{} of type {} after IEND untouched",
+            file_path.display(),
+            after.len(),
+            if after.len() == 1 { "" } else { "s" },
+            chunk_type
+        );
+    }
+    match before.first() {
+        Some(&i) => png.remove_chunk_at(i).map(|_| ()),
+        None => Err(Error::ChunkNotFound(format!("{} before IEND", chunk_type))),
+    }
+}
+
+/// Records a change made to `file_path` in the `--log` file if there is one.
+/// The change is already saved, so failing to log it only warns.
+fn log_operation(log: Option<&Path>, command: &str, file_path: &Path, chunk_type: &str) {
+    if let Some(log) = log {
+        if let Err(e) = append_log_line(log, command, file_path, chunk_type) {
+            eprintln!(
+                "warning: could not write to the log {}: {}",
+                log.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Appends a tab separated line: seconds since the Unix epoch, command, file and chunk type
+fn append_log_line(log: &Path, command: &str, file_path: &Path, chunk_type: &str) -> Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let line = format!(
+        "{}\t{}\t{}\t{}\n",
+        timestamp,
+        command,
+        file_path.display(),
+        chunk_type
+    );
+
+    // a single write keeps the lines of parallel jobs whole
+    let mut file = OpenOptions::new().create(true).append(true).open(log)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Runs `op` on every file in turn, or on `jobs` files at once when set, passing it where to
+/// write its output.
+///
+/// The first error aborts the batch, unless `keep_going` is set: then every file is
+/// processed and the failures are reported together at the end. Parallel jobs can't be
+/// stopped at the first error, so with `jobs` set the batch always behaves like `keep_going`.
+fn for_each_file<F>(
+    file_paths: &[PathBuf],
+    keep_going: bool,
+    jobs: Option<usize>,
+    op: F,
+) -> Result<()>
+where
+    F: Fn(&Path, &mut dyn Write) -> Result<()> + Sync,
+{
+    let keep_going = keep_going || jobs.is_some();
+    let outcomes: Box<dyn Iterator<Item = (&PathBuf, Result<()>)>> = match jobs {
+        None => Box::new(
+            file_paths
+                .iter()
+                .map(|file_path| (file_path, op(file_path, &mut io::stdout().lock()))),
+        ),
+        Some(jobs) => Box::new(run_parallel(file_paths, jobs, &op)?.into_iter().map(
+            |(file_path, output, result)| {
+                let written = io::stdout().write_all(&output).map_err(Error::from);
+                (file_path, written.and(result))
+            },
+        )),
+    };
+
+    let mut failures = Vec::new();
+    for (file_path, result) in outcomes {
+        if let Err(e) = result {
+            if !keep_going {
+                return Err(e);
+            }
+            failures.push((file_path, e));
+        }
+    }
+
+    if failures.is_empty() {
+        return Ok(());
+    }
+    eprintln!("{} of {} files failed:", failures.len(), file_paths.len());
+    for (file_path, e) in &failures {
+        eprintln!("  {}: {}", file_path.display(), e);
+    }
+    Err(Error::Custom(format!(
+        "{} of {} files failed",
+        failures.len(),
+        file_paths.len()
+    )))
+}
+
+/// A file processed by `run_parallel`, with the output written for it and how it went
+type Outcome<'a> = (&'a PathBuf, Vec<u8>, Result<()>);
+
+/// Runs `op` on the files in a pool of `jobs` threads, each file with its own output buffer.
+///
+/// The outcomes are sorted by path, so that the output doesn't depend on thread scheduling.
+fn run_parallel<'a, F>(file_paths: &'a [PathBuf], jobs: usize, op: &F) -> Result<Vec<Outcome<'a>>>
+where
+    F: Fn(&Path, &mut dyn Write) -> Result<()> + Sync,
+{
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .map_err(|e| Error::Custom(format!("Could not start {} jobs: {}", jobs, e)))?;
+    let mut sorted: Vec<&PathBuf> = file_paths.iter().collect();
+    sorted.sort();
+
+    Ok(pool.install(|| {
+        sorted
+            .into_par_iter()
+            .map(|file_path| {
+                let mut output = Vec::new();
+                let result = op(file_path, &mut output);
+                (file_path, output, result)
+            })
+            .collect()
+    }))
 }
 
-/// Prints all of the chunks in a PNG file
+/// Asks a yes/no question on the terminal, anything but `y` or `yes` means no
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Prints all of the chunks in each PNG file
+///
+/// Chunks are streamed from the file one at a time, so memory stays bounded by the largest chunk.
 pub fn print_chunks(args: &PrintArgs) -> Result<()> {
-    let png = Png::from_file(&args.file_path)?;
+    if let (Some(min), Some(max)) = (args.min_length, args.max_length) {
+        if min > max {
+            return Err(Error::Custom(format!(
+                "--min-length {} is greater than --max-length {}",
+                min, max
+            )));
+        }
+    }
+    let type_regex = args.type_regex.as_deref().map(Regex::new).transpose()?;
+    let color = use_color(if args.no_color {
+        ColorMode::Never
+    } else {
+        args.color
+    });
+
+    for_each_file(
+        &args.file_paths,
+        args.keep_going,
+        args.jobs,
+        |file_path, out| print_file_chunks(file_path, args, type_regex.as_ref(), color, out),
+    )
+}
+
+fn print_file_chunks(
+    file_path: &Path,
+    args: &PrintArgs,
+    type_regex: Option<&Regex>,
+    color: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let (input, size): (Box<dyn Read>, u64) = if is_url(file_path) {
+        let bytes = fetch(file_path)?;
+        let size = bytes.len() as u64;
+        (Box::new(Cursor::new(bytes)), size)
+    } else {
+        let size = fs::metadata(file_path)?.len();
+        (Box::new(BufReader::new(File::open(file_path)?)), size)
+    };
+    if args.multi {
+        return print_images_chunks(file_path, input, size, args, type_regex, color, out);
+    }
+    // list every chunk, even one whose type code is not made of ASCII letters
+    let mut reader = ChunkReader::new(input)?
+        .check_crc(!args.verify_crc)
+        .check_type(false);
+    if !args.checksum_only {
+        writeln!(out, "File: {}, Size: {}", file_path.display(), size)?;
+    }
+
+    let entries = reader
+        .by_ref()
+        .enumerate()
+        .map(|(i, chunk)| chunk.map(|chunk| (i, chunk)));
+    print_entries(entries, args, type_regex, color, out)?;
+    print_trailing_note(reader.trailing_data_len(), args, out)
+}
+
+/// Lists the chunks of each image of concatenated PNG files, for `print --multi`
+fn print_images_chunks(
+    file_path: &Path,
+    mut input: Box<dyn Read>,
+    size: u64,
+    args: &PrintArgs,
+    type_regex: Option<&Regex>,
+    color: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    let pngs = if args.verify_crc {
+        Png::from_bytes_multi_unchecked(&bytes)?
+    } else {
+        Png::from_bytes_multi(&bytes)?
+    };
+    if !args.checksum_only {
+        writeln!(
+            out,
+            "File: {}, Size: {}, Images: {}",
+            file_path.display(),
+            size,
+            pngs.len()
+        )?;
+    }
+
+    for (n, png) in pngs.iter().enumerate() {
+        if !args.checksum_only {
+            writeln!(out, "Image #{}: {} chunks", n, png.len())?;
+        }
+        let entries = png.chunks().iter().cloned().enumerate().map(Ok);
+        print_entries(entries, args, type_regex, color, out)?;
+        print_trailing_note(png.trailing_data().len() as u64, args, out)?;
+    }
+
+    Ok(())
+}
+
+/// Lists the chunks passing the `print` filters, in the requested order and up to the limit
+fn print_entries(
+    entries: impl Iterator<Item = Result<(usize, Chunk)>>,
+    args: &PrintArgs,
+    type_regex: Option<&Regex>,
+    color: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let mut entries: Box<dyn Iterator<Item = Result<(usize, Chunk)>>> = Box::new(entries);
+    if args.sort_by != SortBy::Index {
+        let mut sorted = entries.collect::<Result<Vec<_>>>()?;
+        match args.sort_by {
+            SortBy::Index => {}
+            SortBy::Size => sorted.sort_by_key(|(_, chunk)| Reverse(chunk.length())),
+            SortBy::Type => sorted.sort_by_key(|(_, chunk)| chunk.chunk_type().bytes()),
+        }
+        entries = Box::new(sorted.into_iter().map(Ok));
+    }
+
+    let limit = args.limit.unwrap_or(usize::MAX);
+    let mut shown = 0;
+    let mut hidden = 0;
+
+    for entry in entries {
+        let (i, chunk) = entry?;
+        if !is_listed(&chunk, args, type_regex) {
+            continue;
+        }
+        if shown >= limit {
+            hidden += 1;
+            continue;
+        }
+        shown += 1;
+
+        if args.checksum_only {
+            writeln!(out, "{}:{:08x}", chunk.chunk_type(), chunk.crc())?;
+        } else {
+            print_chunk_line(i, &chunk, args, color, out)?;
+        }
+    }
+
+    if hidden > 0 && !args.checksum_only {
+        writeln!(out, "  (… {} more)", hidden)?;
+    }
+    Ok(())
+}
+
+/// Notes the bytes found after the last chunk, if there are any
+fn print_trailing_note(trailing: u64, args: &PrintArgs, out: &mut dyn Write) -> Result<()> {
+    if trailing > 0 && !args.checksum_only {
+        writeln!(
+            out,
+            "  (+ {} bytes of trailing data after the last chunk)",
+            trailing
+        )?;
+    }
+    Ok(())
+}
+
+/// Prints the summary line of a chunk listed by `print`
+fn print_chunk_line(
+    i: usize,
+    chunk: &Chunk,
+    args: &PrintArgs,
+    color: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let chunk_type = chunk.chunk_type().to_string();
+    let chunk_type = if chunk.chunk_type().is_critical() {
+        paint(&chunk_type, BOLD_YELLOW, color)
+    } else {
+        paint(&chunk_type, CYAN, color)
+    };
+
+    let crc_note = if args.verify_crc && !chunk.crc_matches(chunk.crc()) {
+        paint(
+            &format!(
+                " ⚠ CRC mismatch (stored {:#010x}, computed {:#010x})",
+                chunk.crc(),
+                chunk.computed_crc()
+            ),
+            RED,
+            color,
+        )
+    } else {
+        String::new()
+    };
+
+    // Standard chunks hold binary data even when it happens to be valid UTF-8
+    let char_count = match chunk.char_count() {
+        Some(count) if args.char_count && !chunk.is_standard() => {
+            format!(", char_count: {}", count)
+        }
+        _ => String::new(),
+    };
+
+    let preview = match args.preview {
+        Some(size) => format!(", preview: {}", preview(chunk.data(), size)),
+        None => String::new(),
+    };
+
+    writeln!(
+        out,
+        "  chunk#{}{{ chunk_type: {}, data_length: {}{}{}}}{}",
+        i,
+        chunk_type,
+        chunk.length(),
+        char_count,
+        preview,
+        crc_note,
+    )?;
+    Ok(())
+}
+
+/// The first `size` bytes of `data`, quoted and escaped if they are printable text
+/// or as hex otherwise.
+/// A trailing `…` marks truncated data.
+fn preview(data: &[u8], size: usize) -> String {
+    let head = &data[..size.min(data.len())];
+    let ellipsis = if head.len() < data.len() { "…" } else { "" };
+
+    let text = match str::from_utf8(head) {
+        Ok(text) => Some(text),
+        // the cut may fall inside a multi-byte character, drop the incomplete tail
+        Err(e) if e.error_len().is_none() => str::from_utf8(&head[..e.valid_up_to()]).ok(),
+        Err(_) => None,
+    };
+    match text.filter(|text| !text.chars().any(|c| c.is_control() && !c.is_whitespace())) {
+        Some(text) => format!("\"{}\"{}", text.escape_debug(), ellipsis),
+        None => format!("{}{}", to_hex(head), ellipsis),
+    }
+}
+
+/// Whether a chunk passes the filters given to `print`
+fn is_listed(chunk: &Chunk, args: &PrintArgs, type_regex: Option<&Regex>) -> bool {
+    let standard = chunk.is_standard();
+    if args.standard_only && !standard || args.custom_only && standard {
+        return false;
+    }
+    if let Some(re) = type_regex {
+        if !re.is_match(&chunk.chunk_type().to_string()) {
+            return false;
+        }
+    }
+    let length = chunk.length();
+    if args.min_length.is_some_and(|min| length < min)
+        || args.max_length.is_some_and(|max| length > max)
+    {
+        return false;
+    }
+
+    true
+}
+
+/// ANSI escape code for ancillary chunk types
+const CYAN: &str = "\x1b[36m";
+/// ANSI escape code highlighting critical chunk types
+const BOLD_YELLOW: &str = "\x1b[1;33m";
+/// ANSI escape code for integrity warnings
+const RED: &str = "\x1b[31m";
+
+/// Resolves a [`ColorMode`] against the terminal and the `NO_COLOR` convention
+fn use_color(mode: ColorMode) -> bool {
+    match mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto => {
+            io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        }
+    }
+}
+
+/// Wraps `text` in the given ANSI color if `enabled`
+fn paint(text: &str, code: &str, enabled: bool) -> String {
+    if enabled {
+        format!("{}{}\x1b[0m", code, text)
+    } else {
+        text.to_owned()
+    }
+}
+
+/// Moves chunks placed after IEND in front of it and saves the result
+pub fn normalize(args: &NormalizeArgs) -> Result<()> {
+    let mut png = read_png(&args.file_path)?;
+    png.normalize();
+    png.write_file(&args.file_path)
+}
+
+/// Splits a payload file across several PNG files, appending one shard to each
+pub fn shard(args: &ShardArgs) -> Result<()> {
+    let chunk_type = ChunkType::from_str(&args.chunk_type)?;
+    let payload = fs::read(&args.payload)?;
+    let shards = codec::shard(&payload, args.into.len())?;
+
+    for (file_path, shard) in args.into.iter().zip(shards) {
+        let mut png = read_png(file_path)?;
+        png.append_chunk(Chunk::new(chunk_type, shard));
+        png.write_file(file_path)?;
+    }
+
+    Ok(())
+}
+
+/// Reassembles a payload split with [`shard`] and writes it to stdout
+pub fn unshard(args: &UnshardArgs) -> Result<()> {
+    let pngs = args.from.iter().map(read_png).collect::<Result<Vec<_>>>()?;
+
+    let mut shards: Vec<&[u8]> = Vec::with_capacity(pngs.len());
+    for (file_path, png) in args.from.iter().zip(&pngs) {
+        let chunk = png.chunk_by_type(&args.chunk_type).ok_or_else(|| {
+            Error::Custom(format!(
+                "{} does not contain a shard of chunk type {}",
+                file_path.display(),
+                args.chunk_type
+            ))
+        })?;
+        shards.push(chunk.data());
+    }
+
+    io::stdout().write_all(&codec::unshard(&shards)?)?;
+    Ok(())
+}
+
+/// Prints the type, length and data of the chunk at a certain position
+pub fn get(args: &GetArgs) -> Result<()> {
+    let png = read_png_checked(&args.file_path, !args.no_crc_check)?;
+    let chunk = png.chunk_at(args.index).ok_or_else(|| {
+        Error::Custom(format!(
+            "Index {} out of range, the file has {} chunks",
+            args.index,
+            png.len()
+        ))
+    })?;
+
     println!(
-        "File: {}, Size: {}",
-        &args.file_path.display(),
-        png.as_bytes().len()
+        "chunk#{}{{ chunk_type: {}, data_length: {}}}",
+        args.index,
+        chunk.chunk_type(),
+        chunk.length(),
     );
+    match chunk.data_as_string() {
+        Ok(text) if !args.hex => println!("data: {}", text),
+        _ => println!("data (hex): {}", to_hex(chunk.data())),
+    }
 
-    for (i, chunk) in png.chunks().iter().enumerate() {
-        println!(
-            "  chunk#{}{{ chunk_type: {}, data_length: {}}}",
-            i,
-            chunk.chunk_type(),
-            chunk.length(),
-        );
+    Ok(())
+}
+
+/// Returns whether a PNG file contains a chunk of the given type, without printing anything
+pub fn has(args: &HasArgs) -> Result<bool> {
+    let png = read_png(&args.file_path)?;
+    Ok(png.contains_chunk_type(&args.chunk_type))
+}
+
+/// Quiet period that must pass after a change before the chunks are printed again
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Prints the chunks of a PNG file, then again every time it changes on disk
+pub fn watch(args: &WatchArgs) -> Result<()> {
+    let print_args = PrintArgs {
+        file_paths: vec![args.file_path.clone()],
+        ..Default::default()
+    };
+    print_chunks(&print_args)?;
+
+    let file_name = args
+        .file_path
+        .file_name()
+        .ok_or_else(|| Error::Custom(format!("{} is not a file path", args.file_path.display())))?;
+    let dir = match args.file_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    // watch the directory, since saving through a rename replaces the watched file
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    for event in &rx {
+        if !event?
+            .paths
+            .iter()
+            .any(|path| path.file_name() == Some(file_name))
+        {
+            continue;
+        }
+
+        // swallow the burst of events a single save produces
+        while rx.recv_timeout(WATCH_DEBOUNCE).is_ok() {}
+
+        println!();
+        if let Err(e) = print_chunks(&print_args) {
+            eprintln!("Error: {}", e);
+        }
     }
 
     Ok(())
 }
 
+/// Writes a copy of a PNG file, without its custom chunks if asked to
+pub fn export(args: &ExportArgs) -> Result<()> {
+    let mut png = read_png(&args.file_path)?;
+    if args.no_messages {
+        png.retain_chunks(|chunk| chunk.is_standard());
+    }
+    png.write_file(&args.output_file)
+}
+
+/// Writes the chunk byte stream of a PNG file to stdout, signature included only if asked to
+pub fn raw_chunks(args: &RawChunksArgs) -> Result<()> {
+    let png = read_png(&args.file_path)?;
+    let bytes = png.as_bytes();
+    let start = if args.with_signature {
+        0
+    } else {
+        Png::STANDARD_HEADER.len()
+    };
+
+    io::stdout().write_all(&bytes[start..])?;
+    Ok(())
+}
+
+/// Removes duplicated chunks from a PNG file and saves the result if any were found
+pub fn dedup(args: &DedupArgs) -> Result<()> {
+    let mut png = read_png(&args.file_path)?;
+    let removed = png.dedup_chunks();
+    println!(
+        "Removed {} duplicate chunk{}",
+        removed,
+        if removed == 1 { "" } else { "s" }
+    );
+
+    if removed > 0 {
+        png.write_file(&args.file_path)?;
+    }
+    Ok(())
+}
+
+/// Prints every structural problem of a PNG file, failing if there is any
+pub fn lint(args: &LintArgs) -> Result<()> {
+    // a wrong CRC is one more issue to report, not a reason to stop
+    let png = Png::from_file_unchecked(&args.file_path)?;
+    let issues = png.lint();
+    if issues.is_empty() {
+        println!("No issues found");
+        return Ok(());
+    }
+
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    Err(Error::Custom(format!(
+        "{} issue{} found",
+        issues.len(),
+        if issues.len() == 1 { "" } else { "s" }
+    )))
+}
+
+/// Appends a copy of a chunk of one PNG file to another one and saves it
+pub fn copy_chunk(args: &CopyChunkArgs) -> Result<()> {
+    let src = read_png(&args.src)?;
+    let chunk = src
+        .chunk_by_type(&args.chunk_type)
+        .ok_or_else(|| Error::ChunkNotFound(args.chunk_type.clone()))?;
+
+    let mut dst = read_png(&args.dst)?;
+    dst.append_chunk(chunk.clone());
+    dst.write_file(&args.dst)
+}
+
+/// Prints a summary of a PNG file, with "unknown" for what its chunks don't tell
+pub fn info(args: &InfoArgs) -> Result<()> {
+    let png = read_png(&args.file_path)?;
+    let unknown = || "unknown".to_owned();
+
+    println!("File: {}", args.file_path.display());
+    println!("Size: {} bytes", fs::metadata(&args.file_path)?.len());
+    println!(
+        "Dimensions: {}",
+        png.dimensions()
+            .map_or_else(unknown, |(width, height)| format!("{}x{}", width, height))
+    );
+    println!(
+        "Color type: {}",
+        png.color_type().map_or_else(unknown, |color_type| format!(
+            "{} ({})",
+            color_type,
+            color_type_name(color_type)
+        ))
+    );
+    println!(
+        "Bit depth: {}",
+        png.bit_depth()
+            .map_or_else(unknown, |depth| depth.to_string())
+    );
+    println!(
+        "Palette: {}",
+        match png.palette() {
+            Ok(Some(palette)) => format!("yes ({} colors)", palette.len()),
+            Ok(None) => "no".to_owned(),
+            Err(_) => "invalid".to_owned(),
+        }
+    );
+    println!("Chunks: {}", png.len());
+    println!(
+        "Custom chunks: {}",
+        png.chunks()
+            .iter()
+            .filter(|chunk| !chunk.is_standard())
+            .count()
+    );
+
+    Ok(())
+}
+
+/// Name of an `IHDR` color type code
+fn color_type_name(color_type: u8) -> &'static str {
+    match color_type {
+        0 => "grayscale",
+        2 => "RGB",
+        3 => "indexed",
+        4 => "grayscale with alpha",
+        6 => "RGB with alpha",
+        _ => "invalid",
+    }
+}
+
+/// Prints whether a string is a valid chunk type along with its four property bits
+pub fn validate_type(args: &ValidateTypeArgs) -> Result<()> {
+    for line in chunk_type_report(&args.chunk_type)? {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Lines describing the chunk type `s`, or an error explaining why it can't be one
+fn chunk_type_report(s: &str) -> Result<Vec<String>> {
+    if s.len() != 4 {
+        return Err(Error::Custom(format!(
+            "'{}' is not a chunk type: it is {} bytes long, chunk types are exactly 4 ASCII letters",
+            s,
+            s.len()
+        )));
+    }
+    if let Some(c) = s.chars().find(|c| !c.is_ascii_alphabetic()) {
+        return Err(Error::Custom(format!(
+            "'{}' is not a chunk type: {:?} is not an ASCII letter (A-Z or a-z)",
+            s, c
+        )));
+    }
+
+    let chunk_type = ChunkType::from_str(s)?;
+    let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+    let case = |upper: bool| if upper { "uppercase" } else { "lowercase" };
+    Ok(vec![
+        format!("Chunk type: {}", chunk_type),
+        if chunk_type.is_reserved_bit_valid() {
+            "Valid: yes".to_owned()
+        } else {
+            "Valid: no, the reserved bit is set (the third letter must be uppercase)".to_owned()
+        },
+        format!(
+            "Critical: {} (first letter {})",
+            yes_no(chunk_type.is_critical()),
+            case(chunk_type.is_critical())
+        ),
+        format!(
+            "Public: {} (second letter {})",
+            yes_no(chunk_type.is_public()),
+            case(chunk_type.is_public())
+        ),
+        format!(
+            "Reserved bit clear: {} (third letter {})",
+            yes_no(chunk_type.is_reserved_bit_valid()),
+            case(chunk_type.is_reserved_bit_valid())
+        ),
+        format!(
+            "Safe to copy: {} (fourth letter {})",
+            yes_no(chunk_type.is_safe_to_copy()),
+            case(!chunk_type.is_safe_to_copy())
+        ),
+        // `ChunkType::is_valid` also asks for a private type, as messages shouldn't take
+        // names the PNG specification may define
+        format!(
+            "Usable for a private message chunk: {}",
+            yes_no(chunk_type.is_valid())
+        ),
+    ])
+}
+
+/// Browses the chunks of a PNG file in an interactive terminal UI
+#[cfg(feature = "tui")]
+pub fn tui(args: &crate::args::TuiArgs) -> Result<()> {
+    let png = read_png(&args.file_path)?;
+    crate::tui::run(&png)
+}
+
+/// Moves a chunk to another position and saves the result
+pub fn move_chunk(args: &MoveArgs) -> Result<()> {
+    let mut png = read_png(&args.file_path)?;
+    png.move_chunk(args.from, args.to)?;
+    png.write_file(&args.file_path)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::path::PathBuf;
+    use std::sync::Mutex;
 
     const IMG_PATH: &str = "assets/imgs";
 
     fn testing_origin_path() -> PathBuf {
-        let mut path: PathBuf = IMG_PATH.try_into().unwrap();
+        let mut path: PathBuf = IMG_PATH.into();
         path.push("test.png");
         path
     }
 
     fn testing_out_path() -> PathBuf {
-        let mut path: PathBuf = IMG_PATH.try_into().unwrap();
+        let mut path: PathBuf = IMG_PATH.into();
 
         path.push("test_out.png");
         path
@@ -90,6 +1374,246 @@ mod tests {
         (file_path, chunk_type, message, output_file)
     }
 
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview(b"hello", 10), "\"hello\"");
+        assert_eq!(preview(b"hello", 2), "\"he\"…");
+        assert_eq!(preview(b"a\tb\n", 4), "\"a\\tb\\n\"");
+        assert_eq!(preview(b"a\0b", 3), "610062");
+        assert_eq!(preview("é".as_bytes(), 1), "\"\"…");
+        assert_eq!(preview(&[0xff, 0x01], 2), "ff01");
+    }
+
+    #[test]
+    fn test_is_listed_by_length() {
+        let chunk = Chunk::from_strings("ruSt", "hello").unwrap();
+        let listed = |min_length, max_length| {
+            let args = PrintArgs {
+                min_length,
+                max_length,
+                ..Default::default()
+            };
+            is_listed(&chunk, &args, None)
+        };
+
+        assert!(listed(None, None));
+        assert!(listed(Some(5), Some(5)));
+        assert!(listed(Some(1), None));
+        assert!(!listed(Some(6), None));
+        assert!(!listed(None, Some(4)));
+    }
+
+    #[test]
+    fn test_describe_nested() {
+        let mut png = Png::from_chunks(vec![Chunk::from_strings("ruSt", "innermost").unwrap()]);
+        for _ in 0..MAX_NESTING_DEPTH + 1 {
+            png = Png::from_chunks(vec![
+                Chunk::from_strings("IEND", "").unwrap(),
+                Chunk::new(ChunkType::from_str("neSt").unwrap(), png.as_bytes()),
+            ]);
+        }
+
+        let mut lines = Vec::new();
+        describe_nested(&png.as_bytes(), 1, &DecodeArgs::default(), &mut lines);
+        assert_eq!(lines.len(), 2 * MAX_NESTING_DEPTH);
+        assert_eq!(lines[0], "  [level 1] IEND (0 bytes)");
+        assert!(lines[1].starts_with("  [level 1] neSt: nested PNG ("));
+        assert_eq!(
+            lines.last().unwrap().trim_start(),
+            "[level 8] neSt: nested PNG, not expanded past level 8"
+        );
+
+        let mut lines = Vec::new();
+        let inner = Png::from_chunks(vec![Chunk::from_strings("ruSt", "hi").unwrap()]);
+        describe_nested(&inner.as_bytes(), 1, &DecodeArgs::default(), &mut lines);
+        assert_eq!(lines, ["  [level 1] ruSt: hi"]);
+
+        let mut lines = Vec::new();
+        describe_nested(&Png::STANDARD_HEADER, 2, &DecodeArgs::default(), &mut lines);
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn test_remove_before_iend() {
+        let mut png = Png::from_chunks(vec![
+            Chunk::from_strings("IHDR", "header").unwrap(),
+            Chunk::from_strings("ruSt", "before").unwrap(),
+            Chunk::from_strings("IEND", "").unwrap(),
+            Chunk::from_strings("ruSt", "after").unwrap(),
+        ]);
+
+        remove_before_iend(&mut png, Path::new("test.png"), "ruSt").unwrap();
+        assert_eq!(png.positions_by_type("ruSt"), [2]);
+        let err = remove_before_iend(&mut png, Path::new("test.png"), "ruSt").unwrap_err();
+        assert!(matches!(err, Error::ChunkNotFound(_)));
+        assert_eq!(png.len(), 3);
+    }
+
+    #[test]
+    fn test_on_exists() {
+        assert_eq!(on_exists(&EncodeArgs::default()), OnExists::Append);
+        let args = EncodeArgs {
+            on_exists: OnExists::Skip,
+            ..Default::default()
+        };
+        assert_eq!(on_exists(&args), OnExists::Skip);
+        let args = EncodeArgs {
+            upsert: true,
+            ..Default::default()
+        };
+        assert_eq!(on_exists(&args), OnExists::Replace);
+    }
+
+    #[test]
+    fn test_find_duplicate() {
+        let png = Png::from_chunks(vec![
+            Chunk::from_strings("IHDR", "header").unwrap(),
+            Chunk::from_strings("ruSt", "secret").unwrap(),
+            Chunk::from_strings("IEND", "").unwrap(),
+        ]);
+
+        let same_data = Chunk::from_strings("otHr", "secret").unwrap();
+        assert_eq!(find_duplicate(&png, &same_data), Some(1));
+        let other_data = Chunk::from_strings("ruSt", "another secret").unwrap();
+        assert_eq!(find_duplicate(&png, &other_data), None);
+        let empty = Chunk::from_strings("ruSt", "").unwrap();
+        assert_eq!(find_duplicate(&png, &empty), None);
+    }
+
+    #[test]
+    fn test_check_ascii() {
+        assert!(check_ascii(b"plain message\n").is_ok());
+        assert_eq!(
+            check_ascii("café".as_bytes()).unwrap_err().to_string(),
+            "The message has the non-ASCII byte 0xc3 at position 3, refused by --ascii-only"
+        );
+    }
+
+    #[test]
+    fn test_message_encodings() {
+        let latin1 = encode_text("café", MessageEncoding::Latin1).unwrap();
+        assert_eq!(latin1, b"caf\xe9");
+        assert_eq!(
+            decode_text(latin1, MessageEncoding::Latin1).unwrap(),
+            "café"
+        );
+        assert!(encode_text("€", MessageEncoding::Latin1).is_err());
+
+        let hex = encode_text("de ad\nBE EF\n", MessageEncoding::Hex).unwrap();
+        assert_eq!(hex, [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(decode_text(hex, MessageEncoding::Hex).unwrap(), "deadbeef");
+        assert!(encode_text("abc", MessageEncoding::Hex).is_err());
+        assert!(encode_text("zz", MessageEncoding::Hex).is_err());
+        assert!(encode_text("+1", MessageEncoding::Hex).is_err());
+
+        assert_eq!(encode_text("hi", MessageEncoding::Utf8).unwrap(), b"hi");
+    }
+
+    #[test]
+    fn test_append_log_line() {
+        let log = std::env::temp_dir().join("pngchat_test_append_log_line.log");
+        let _ = fs::remove_file(&log);
+
+        append_log_line(&log, "encode", Path::new("a.png"), "ruSt").unwrap();
+        append_log_line(&log, "remove", Path::new("b.png"), "ruSt").unwrap();
+
+        let content = fs::read_to_string(&log).unwrap();
+        let lines: Vec<Vec<&str>> = content
+            .lines()
+            .map(|line| line.split('\t').collect())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0][0].parse::<u64>().is_ok());
+        assert_eq!(lines[0][1..], ["encode", "a.png", "ruSt"]);
+        assert_eq!(lines[1][1..], ["remove", "b.png", "ruSt"]);
+        fs::remove_file(&log).unwrap();
+    }
+
+    #[test]
+    fn test_for_each_file_keep_going() {
+        let files: Vec<PathBuf> = vec!["a.png".into(), "b.png".into(), "c.png".into()];
+        let fail_on_b = |file_path: &Path| match file_path.to_str() {
+            Some("b.png") => Err(Error::Custom("bad file".to_owned())),
+            _ => Ok(()),
+        };
+
+        let visited = Mutex::new(Vec::new());
+        let result = for_each_file(&files, false, None, |file_path, _| {
+            visited.lock().unwrap().push(file_path.to_owned());
+            fail_on_b(file_path)
+        });
+        assert!(result.is_err());
+        assert_eq!(visited.lock().unwrap().len(), 2);
+
+        visited.lock().unwrap().clear();
+        let result = for_each_file(&files, true, None, |file_path, _| {
+            visited.lock().unwrap().push(file_path.to_owned());
+            fail_on_b(file_path)
+        });
+        assert_eq!(result.unwrap_err().to_string(), "1 of 3 files failed");
+        assert_eq!(*visited.lock().unwrap(), files);
+
+        let result = for_each_file(&files, true, Some(2), |file_path, _| fail_on_b(file_path));
+        assert_eq!(result.unwrap_err().to_string(), "1 of 3 files failed");
+    }
+
+    #[test]
+    fn test_remove_jobs_reports_every_file() {
+        let dir = std::env::temp_dir().join("pngchat_test_remove_jobs");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut png = Png::new_minimal(1, 1);
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"hi".to_vec(),
+        ));
+        let files: Vec<PathBuf> = ["j1.png", "j2.png", "j3.png"]
+            .iter()
+            .map(|name| dir.join(name))
+            .collect();
+        png.write_file(&files[0]).unwrap();
+        fs::write(&files[1], b"not a png").unwrap();
+        png.write_file(&files[2]).unwrap();
+
+        let args = RemoveArgs {
+            file_paths: files.clone(),
+            chunk_type: "ruSt".to_owned(),
+            yes: true,
+            jobs: Some(2),
+            ..Default::default()
+        };
+        let result = remove(&args);
+        assert_eq!(result.unwrap_err().to_string(), "1 of 3 files failed");
+
+        // every file but the broken one was modified, as the failure is reported not aborting
+        for file_path in [&files[0], &files[2]] {
+            assert!(Png::from_file(file_path)
+                .unwrap()
+                .chunk_by_type("ruSt")
+                .is_none());
+        }
+        assert_eq!(fs::read(&files[1]).unwrap(), b"not a png");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_parallel_sorts_outcomes() {
+        let files: Vec<PathBuf> = vec!["c.png".into(), "a.png".into(), "b.png".into()];
+        let outcomes = run_parallel(&files, 3, &|file_path: &Path, out: &mut dyn Write| {
+            write!(out, "{}", file_path.display())?;
+            Ok(())
+        })
+        .unwrap();
+
+        let outputs: Vec<&[u8]> = outcomes
+            .iter()
+            .map(|(_, output, _)| output.as_slice())
+            .collect();
+        assert_eq!(outputs, [&b"a.png"[..], b"b.png", b"c.png"]);
+    }
+
     #[ignore]
     #[test]
     fn test_read_png() {
@@ -120,18 +1644,22 @@ mod tests {
 
         let encode_args = EncodeArgs {
             file_path: file_path.clone(),
-            chunk_type: chunk_type.clone(),
-            message,
+            chunk_type: Some(chunk_type.clone()),
+            message: Some(message),
             output_file: None,
+            ..Default::default()
         };
 
         let remove_args = RemoveArgs {
-            file_path: file_path.clone(),
+            file_paths: vec![file_path.clone()],
             chunk_type: chunk_type.clone(),
+            yes: true,
+            ..Default::default()
         };
 
         let print_origin_arg = PrintArgs {
-            file_path: file_path.clone(),
+            file_paths: vec![file_path.clone()],
+            ..Default::default()
         };
 
         println!("Before encoding...");
@@ -151,19 +1679,23 @@ mod tests {
 
         let encode_args = EncodeArgs {
             file_path: file_path.clone(),
-            chunk_type: chunk_type.clone(),
-            message,
+            chunk_type: Some(chunk_type.clone()),
+            message: Some(message),
             output_file: Some(output_file.clone()),
+            ..Default::default()
         };
 
         let decode_args = DecodeArgs {
             file_path: output_file.clone(),
-            chunk_type: chunk_type.clone(),
+            chunk_type: Some(chunk_type.clone()),
+            ..Default::default()
         };
 
         let remove_args = RemoveArgs {
-            file_path: output_file.clone(),
+            file_paths: vec![output_file.clone()],
             chunk_type: chunk_type.clone(),
+            yes: true,
+            ..Default::default()
         };
 
         encode(&encode_args).unwrap();
@@ -178,18 +1710,22 @@ mod tests {
 
         let encode_args = EncodeArgs {
             file_path: file_path.clone(),
-            chunk_type: chunk_type.clone(),
-            message,
+            chunk_type: Some(chunk_type.clone()),
+            message: Some(message),
             output_file: Some(output_file.clone()),
+            ..Default::default()
         };
 
         let remove_args = RemoveArgs {
-            file_path: output_file.clone(),
+            file_paths: vec![output_file.clone()],
             chunk_type: chunk_type.clone(),
+            yes: true,
+            ..Default::default()
         };
 
         let print_out_args = PrintArgs {
-            file_path: output_file.clone(),
+            file_paths: vec![output_file.clone()],
+            ..Default::default()
         };
 
         encode(&encode_args).unwrap();
@@ -207,14 +1743,53 @@ mod tests {
         let (file_path, _, _, output_file) = testing_args();
 
         let print_origin_arg = PrintArgs {
-            file_path: file_path.clone(),
+            file_paths: vec![file_path.clone()],
+            ..Default::default()
         };
 
         let print_out_args = PrintArgs {
-            file_path: output_file.clone(),
+            file_paths: vec![output_file.clone()],
+            ..Default::default()
         };
 
         print_chunks(&print_origin_arg).unwrap();
         print_chunks(&print_out_args).unwrap();
     }
+
+    #[test]
+    fn test_chunk_type_report() {
+        let lines = chunk_type_report("ruSt").unwrap();
+        assert_eq!(lines[0], "Chunk type: ruSt");
+        assert_eq!(lines[1], "Valid: yes");
+        assert_eq!(lines[2], "Critical: no (first letter lowercase)");
+        assert_eq!(lines[5], "Safe to copy: yes (fourth letter lowercase)");
+        assert_eq!(lines[6], "Usable for a private message chunk: yes");
+
+        let lines = chunk_type_report("IHDR").unwrap();
+        assert_eq!(lines[3], "Public: yes (second letter uppercase)");
+        assert_eq!(lines[5], "Safe to copy: no (fourth letter uppercase)");
+
+        assert_eq!(lines[1], "Valid: yes");
+        assert_eq!(lines[6], "Usable for a private message chunk: no");
+
+        let lines = chunk_type_report("rust").unwrap();
+        assert_eq!(
+            lines[1],
+            "Valid: no, the reserved bit is set (the third letter must be uppercase)"
+        );
+        assert_eq!(lines[6], "Usable for a private message chunk: no");
+
+        let lines = chunk_type_report("rUSt").unwrap();
+        assert_eq!(lines[1], "Valid: yes");
+        assert_eq!(lines[6], "Usable for a private message chunk: no");
+    }
+
+    #[test]
+    fn test_chunk_type_report_invalid() {
+        let err = chunk_type_report("abc").unwrap_err().to_string();
+        assert!(err.contains("3 bytes long"));
+
+        let err = chunk_type_report("ru5t").unwrap_err().to_string();
+        assert!(err.contains("'5' is not an ASCII letter"));
+    }
 }
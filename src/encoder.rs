@@ -0,0 +1,162 @@
+//! Message encoding settings set up once and reused
+//!
+//! An [`Encoder`] holds a chunk type and a chain of [codecs](crate::codec::Codec) applied to
+//! every message it stores, a [`Decoder`] reverts the same chain.
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::codec::Codec;
+use crate::png::Png;
+use crate::{Error, Result};
+
+/// Stores messages in chunks of one type through a chain of codecs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Encoder {
+    chunk_type: ChunkType,
+    codecs: Vec<Codec>,
+}
+
+impl Encoder {
+    /// Creates an `Encoder` storing messages as they are in chunks of `chunk_type`
+    pub fn new(chunk_type: ChunkType) -> Encoder {
+        Encoder {
+            chunk_type,
+            codecs: Vec::new(),
+        }
+    }
+
+    /// Adds a codec, applied after the ones added before
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.codecs.push(codec);
+        self
+    }
+
+    /// Type of the chunks holding the messages
+    pub fn chunk_type(&self) -> ChunkType {
+        self.chunk_type
+    }
+
+    /// Builds the chunk holding `message`
+    pub fn chunk(&self, message: &[u8]) -> Result<Chunk> {
+        self.codecs
+            .iter()
+            .fold(
+                Chunk::builder().chunk_type(self.chunk_type).data(message),
+                |builder, codec| builder.codec(codec.clone()),
+            )
+            .build()
+    }
+
+    /// Appends the chunk holding `message` to `png`
+    pub fn encode_into(&self, png: &mut Png, message: &[u8]) -> Result<()> {
+        png.append_chunk(self.chunk(message)?);
+        Ok(())
+    }
+
+    /// The `Decoder` reading back the messages of this `Encoder`
+    pub fn decoder(&self) -> Decoder {
+        Decoder {
+            chunk_type: self.chunk_type,
+            codecs: self.codecs.clone(),
+        }
+    }
+}
+
+/// Reads messages stored by an [`Encoder`] with the same chunk type and codecs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decoder {
+    chunk_type: ChunkType,
+    codecs: Vec<Codec>,
+}
+
+impl Decoder {
+    /// Creates a `Decoder` reading messages stored as they are in chunks of `chunk_type`
+    pub fn new(chunk_type: ChunkType) -> Decoder {
+        Decoder {
+            chunk_type,
+            codecs: Vec::new(),
+        }
+    }
+
+    /// Adds a codec, in the order they were applied when encoding
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.codecs.push(codec);
+        self
+    }
+
+    /// Type of the chunks holding the messages
+    pub fn chunk_type(&self) -> ChunkType {
+        self.chunk_type
+    }
+
+    /// Recovers the message from chunk data, undoing the last codec first
+    pub fn decode_data(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.codecs
+            .iter()
+            .rev()
+            .try_fold(data.to_vec(), |data, codec| codec.decode(&data))
+    }
+
+    /// Recovers the message of the first chunk of the type in `png`
+    pub fn decode_from(&self, png: &Png) -> Result<Vec<u8>> {
+        let chunk_type = self.chunk_type.to_string();
+        let chunk = png
+            .chunk_by_type(&chunk_type)
+            .ok_or(Error::ChunkNotFound(chunk_type))?;
+        self.decode_data(chunk.data())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_encoder() -> Encoder {
+        Encoder::new(ChunkType::from_str("ruSt").unwrap())
+            .codec(Codec::Deflate)
+            .codec(Codec::Hmac(b"key".to_vec()))
+            .codec(Codec::Pad(128))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let encoder = testing_encoder();
+        let mut png = Png::new_minimal(1, 1);
+        encoder.encode_into(&mut png, b"first message").unwrap();
+        encoder.encode_into(&mut png, b"second message").unwrap();
+
+        let chunks = png.chunks_by_type("ruSt");
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].length(), 128);
+
+        let decoder = encoder.decoder();
+        assert_eq!(decoder.decode_from(&png).unwrap(), b"first message");
+        assert_eq!(
+            decoder.decode_data(chunks[1].data()).unwrap(),
+            b"second message"
+        );
+    }
+
+    #[test]
+    fn test_decoder_new_matches_encoder() {
+        let decoder = Decoder::new(ChunkType::from_str("ruSt").unwrap())
+            .codec(Codec::Deflate)
+            .codec(Codec::Hmac(b"key".to_vec()))
+            .codec(Codec::Pad(0));
+        let chunk = testing_encoder().chunk(b"message").unwrap();
+        assert_eq!(decoder.decode_data(chunk.data()).unwrap(), b"message");
+
+        let wrong_key = Decoder::new(ChunkType::from_str("ruSt").unwrap())
+            .codec(Codec::Hmac(b"other key".to_vec()))
+            .codec(Codec::Pad(0));
+        assert!(wrong_key.decode_data(chunk.data()).is_err());
+    }
+
+    #[test]
+    fn test_decode_missing_chunk() {
+        let decoder = testing_encoder().decoder();
+        let err = decoder.decode_from(&Png::new_minimal(1, 1)).unwrap_err();
+        assert!(matches!(err, Error::ChunkNotFound(ty) if ty == "ruSt"));
+    }
+}
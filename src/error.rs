@@ -5,9 +5,15 @@
 //! # #[allow(dead_code)]
 //! pub enum Error<'a> {
 //!    Custom(&'a str),
+//!    ChunkNotFound(String),
 //!    IO(std::io::Error),
 //!    Fmt(std::fmt::Error),
 //!    FromUtf8Error(std::string::FromUtf8Error),
+//!    Regex(regex::Error),
+//!    Watch(notify::Error),
+//!    TryFromInt(std::num::TryFromIntError),
+//!    #[cfg(feature = "net")]
+//!    Net(Box<ureq::Error>),
 //! }
 //! ```
 
@@ -22,6 +28,8 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub enum Error {
     /// Errors which can occur when other case
     Custom(String),
+    /// Errors which can occur when no chunk of the given type exists
+    ChunkNotFound(String),
     /// Errors which can occur when doing io operation
     IO(std::io::Error),
     /// Errors which can occur when formating
@@ -30,16 +38,31 @@ pub enum Error {
     FromUtf8Error(std::string::FromUtf8Error),
     /// Errors which can occur when attempting to interpret a sequence of `[u8]` as a str.
     Utf8Err(std::str::Utf8Error),
+    /// Errors which can occur when compiling a regular expression
+    Regex(regex::Error),
+    /// Errors which can occur when watching a file for changes
+    Watch(notify::Error),
+    /// Errors which can occur when a size does not fit in the integer type of a PNG field
+    TryFromInt(std::num::TryFromIntError),
+    /// Errors which can occur when downloading a file
+    #[cfg(feature = "net")]
+    Net(Box<ureq::Error>),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Custom(s) => write!(f, "{}", s),
+            Self::ChunkNotFound(s) => write!(f, "No chunk of type {} found", s),
             Self::IO(e) => write!(f, "{}", e),
             Self::Fmt(e) => write!(f, "{}", e),
             Self::Utf8Err(e) => write!(f, "{}", e),
             Self::FromUtf8Error(e) => write!(f, "{}", e),
+            Self::Regex(e) => write!(f, "{}", e),
+            Self::Watch(e) => write!(f, "{}", e),
+            Self::TryFromInt(e) => write!(f, "Size out of range: {}", e),
+            #[cfg(feature = "net")]
+            Self::Net(e) => write!(f, "{}", e),
         }
     }
 }
@@ -68,4 +91,68 @@ impl From<std::string::FromUtf8Error> for Error {
     }
 }
 
-impl error::Error for Error {}
+impl From<regex::Error> for Error {
+    fn from(e: regex::Error) -> Self {
+        Self::Regex(e)
+    }
+}
+
+impl From<notify::Error> for Error {
+    fn from(e: notify::Error) -> Self {
+        Self::Watch(e)
+    }
+}
+
+impl From<std::num::TryFromIntError> for Error {
+    fn from(e: std::num::TryFromIntError) -> Self {
+        Self::TryFromInt(e)
+    }
+}
+
+#[cfg(feature = "net")]
+impl From<Box<ureq::Error>> for Error {
+    fn from(e: Box<ureq::Error>) -> Self {
+        Self::Net(e)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Custom(_) | Self::ChunkNotFound(_) => None,
+            Self::IO(e) => Some(e),
+            Self::Fmt(e) => Some(e),
+            Self::FromUtf8Error(e) => Some(e),
+            Self::Utf8Err(e) => Some(e),
+            Self::Regex(e) => Some(e),
+            Self::Watch(e) => Some(e),
+            Self::TryFromInt(e) => Some(e),
+            #[cfg(feature = "net")]
+            Self::Net(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+    use std::io;
+
+    #[test]
+    fn test_source_exposes_wrapped_error() {
+        let err = Error::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert_eq!(err.source().unwrap().to_string(), "missing");
+
+        let err = Error::Custom("plain".to_owned());
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn test_try_from_int_error() {
+        let err = Error::from(u32::try_from(u64::MAX).unwrap_err());
+        assert!(matches!(err, Error::TryFromInt(_)));
+        assert!(err.to_string().starts_with("Size out of range"));
+        assert!(err.source().is_some());
+    }
+}
@@ -18,7 +18,7 @@
 //! # Getting Started
 //!
 //! > Note: If you don’t have Rust yet, I recommend you use [`rustup`](https://rustup.rs/) to manage your Rust installation.
-//! [The official rust guide](https://doc.rust-lang.org/book/ch01-01-installation.html) has a wonderful section on getting started.
+//! > [The official rust guide](https://doc.rust-lang.org/book/ch01-01-installation.html) has a wonderful section on getting started.
 //!
 //! First you need to make sure your rust toolchain is up to date. For the latest run
 //!
@@ -61,6 +61,8 @@
 //! ```bash
 //! # Encodes a message into a PNG file and saves the result
 //! pngchat encode ./test.png ruSt "This is a hidden message"
+//! # The message can also be piped through stdin
+//! echo "This is a hidden message" | pngchat encode ./test.png ruSt -
 //!
 //! # Searches for a message hidden in a PNG file and prints the message if one is found
 //! pngchat decode ./test.png ruSt
@@ -70,6 +72,110 @@
 //!
 //! # Prints all of the chunks in a PNG file
 //! pngchat print ./test.png
+//!
+//! # Moves chunks placed after IEND in front of it and saves the result
+//! pngchat normalize ./test.png
+//!
+//! # Splits a payload across several PNG files and reassembles it
+//! pngchat shard ./payload.bin --into a.png b.png c.png --type ruSt
+//! pngchat unshard --from a.png b.png c.png --type ruSt > payload.bin
+//!
+//! # Prints the chunk at a certain position
+//! pngchat get ./test.png --index 3
+//!
+//! # Exits with 0 if a chunk type exists, useful in shell conditionals
+//! pngchat has ./test.png ruSt && pngchat decode ./test.png ruSt
+//!
+//! # Prints the chunks again every time the file changes
+//! pngchat watch ./test.png
+//!
+//! # Removes a chunk from many files, reporting every failure at the end
+//! pngchat remove ./imgs/*.png ruSt --keep-going
+//!
+//! # Writes a clean copy without any custom chunk, safe to publish
+//! pngchat export ./test.png --no-messages ./clean.png
+//!
+//! # Writes readable tEXt metadata instead of a hidden message
+//! pngchat encode ./test.png tEXt "Made with pngchat" --text Comment
+//!
+//! # Dumps the chunk byte stream without the PNG signature
+//! pngchat raw-chunks ./test.png > chunks.bin
+//!
+//! # Moves the chunk at position 3 to position 1
+//! pngchat move ./test.png --from 3 --to 1
+//!
+//! # Removes chunks left duplicated by repeated encode runs
+//! pngchat dedup ./test.png
+//!
+//! # Saves the result as ./encoded/test.png, leaving the original untouched
+//! pngchat encode ./test.png ruSt "This is a hidden message" --output-dir ./encoded
+//!
+//! # Reports every structural problem, such as chunks after IEND
+//! pngchat lint ./test.png
+//!
+//! # Copies a hidden message to another image without decoding it
+//! pngchat copy-chunk ./test.png ./other.png ruSt
+//!
+//! # Compresses a long message when that makes it smaller, decode it with --compressed
+//! pngchat encode ./test.png ruSt "$(cat notes.txt)" --compress=auto
+//! pngchat decode ./test.png ruSt --compressed
+//!
+//! # Inspects a remote image, needs a build with `--features net`
+//! pngchat print https://example.com/image.png
+//!
+//! # Picks a valid custom chunk type and prints it for decoding later
+//! pngchat encode ./test.png "This is a hidden message" --random-type
+//! # The same seed always picks the same chunk type
+//! pngchat encode ./test.png "This is a hidden message" --random-type --seed 42
+//!
+//! # Encodes without printing the confirmation
+//! pngchat encode ./test.png ruSt "This is a hidden message" --quiet
+//!
+//! # Only lists chunks holding 100 to 4096 bytes of data
+//! pngchat print ./test.png --min-length 100 --max-length 4096
+//!
+//! # Summarizes dimensions, color type, bit depth, palette and chunk counts
+//! pngchat info ./test.png
+//!
+//! # Lists and decodes a whole PNG file hidden in a chunk, and the ones hidden in it
+//! pngchat decode ./test.png neSt --nested
+//!
+//! # Stores raw bytes given as hex, and prints them back the same way
+//! pngchat encode ./test.png ruSt "deadbeef" --encoding hex
+//! pngchat decode ./test.png ruSt --encoding hex
+//!
+//! # Lists a whole folder using 8 threads, still sorted by path
+//! pngchat print ./imgs/*.png --jobs 8
+//!
+//! # Prints how many messages are hidden under a type without revealing them
+//! pngchat decode ./test.png ruSt --count-only
+//!
+//! # Keeps an audit trail of the files changed
+//! pngchat encode ./test.png ruSt "This is a hidden message" --log ./pngchat.log
+//!
+//! # Fails instead of storing a message with non-ASCII bytes
+//! pngchat encode ./test.png ruSt "This is a hidden message" --ascii-only
+//!
+//! # Skips the encode when the message is already stored under any chunk type
+//! pngchat encode ./test.png ruSt "This is a hidden message" --if-absent
+//!
+//! # Replaces the existing ruSt chunk instead of adding another one (error, replace, append or skip)
+//! pngchat encode ./test.png ruSt "This is a new hidden message" --on-exists=replace
+//!
+//! # Only removes a ruSt chunk placed before IEND
+//! pngchat remove ./test.png ruSt --before-iend-only
+//!
+//! # Modifies the file in place without changing its modification time
+//! pngchat remove ./test.png ruSt --preserve-mtime
+//!
+//! # Checks a chunk type name and shows whether it is critical, public and safe to copy
+//! pngchat validate-type ruSt
+//!
+//! # Lists the chunks of each image in a file made of several PNG files concatenated together
+//! pngchat print ./sprites.png --multi
+//!
+//! # Browses the chunks interactively, needs a build with `--features tui`
+//! pngchat tui ./test.png
 //! ```
 //!
 //! # Links
@@ -89,14 +195,26 @@ pub mod commands;
 
 mod chunk;
 mod chunk_type;
+mod codec;
+mod encoder;
+#[cfg(feature = "net")]
+mod net;
 mod png;
+mod reader;
+#[cfg(feature = "tui")]
+mod tui;
 
 mod error;
 mod utils;
 
+pub use chunk::{Chunk, ChunkBuilder};
+pub use chunk_type::ChunkType;
+pub use codec::Codec;
+pub use encoder::{Decoder, Encoder};
 pub use error::{Error, Result};
-pub use png::Png;
-pub use utils::{checksum_32, u8_4_from_slice};
+pub use png::{DecodeResult, LintIssue, Png};
+pub use reader::ChunkReader;
+pub use utils::{checksum_32, from_hex, to_hex, u8_4_from_slice};
 
 /// 4 bytes size
 pub const CHUNK_SIZE: usize = 4;
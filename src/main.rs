@@ -1,18 +1,60 @@
+use std::process;
+
 use clap::Parser;
 
 use pngchat::{
     args::{Commands, PngChatArgs},
-    commands::{decode, encode, print_chunks, remove},
-    Result,
+    commands::{
+        copy_chunk, decode, dedup, encode, export, get, has, info, lint, move_chunk, normalize,
+        print_chunks, raw_chunks, remove, shard, unshard, validate_type, watch,
+    },
+    Error, Result,
 };
 
-fn main() -> Result<()> {
+#[cfg(feature = "tui")]
+use pngchat::commands::tui;
+
+/// Exit code used when the requested chunk type is absent, as opposed to a real failure
+const EXIT_NOT_FOUND: i32 = 2;
+
+fn main() {
     let cli = PngChatArgs::parse();
 
+    if let Err(e) = run(&cli) {
+        eprintln!("Error: {}", e);
+        process::exit(match e {
+            Error::ChunkNotFound(_) => EXIT_NOT_FOUND,
+            _ => 1,
+        });
+    }
+}
+
+fn run(cli: &PngChatArgs) -> Result<()> {
     match &cli.command {
         Commands::Encode(args) => encode(args),
         Commands::Decode(args) => decode(args),
         Commands::Remove(args) => remove(args),
         Commands::Print(args) => print_chunks(args),
+        Commands::Normalize(args) => normalize(args),
+        Commands::Shard(args) => shard(args),
+        Commands::Unshard(args) => unshard(args),
+        Commands::Get(args) => get(args),
+        Commands::Watch(args) => watch(args),
+        Commands::Export(args) => export(args),
+        Commands::RawChunks(args) => raw_chunks(args),
+        Commands::Move(args) => move_chunk(args),
+        Commands::Dedup(args) => dedup(args),
+        Commands::Lint(args) => lint(args),
+        Commands::CopyChunk(args) => copy_chunk(args),
+        Commands::Info(args) => info(args),
+        Commands::ValidateType(args) => validate_type(args),
+        #[cfg(feature = "tui")]
+        Commands::Tui(args) => tui(args),
+        Commands::Has(args) => {
+            if !has(args)? {
+                process::exit(EXIT_NOT_FOUND);
+            }
+            Ok(())
+        }
     }
 }
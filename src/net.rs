@@ -0,0 +1,47 @@
+//! Downloading PNG files over HTTP(S)
+
+use std::io::Read;
+
+use crate::{Error, Result};
+
+/// Largest download accepted, so a misbehaving server can't exhaust memory
+const MAX_DOWNLOAD_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Downloads the body of `url` into memory
+pub fn fetch(url: &str) -> Result<Vec<u8>> {
+    let response = ureq::get(url).call().map_err(Box::new)?;
+    read_limited(response.into_reader(), MAX_DOWNLOAD_BYTES)
+}
+
+/// Reads `reader` to the end, failing if it holds more than `max_bytes`
+fn read_limited(reader: impl Read, max_bytes: u64) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    // read one byte past the limit to tell a body of exactly `max_bytes` from a longer one
+    reader
+        .take(max_bytes.saturating_add(1))
+        .read_to_end(&mut body)?;
+
+    if body.len() as u64 > max_bytes {
+        return Err(Error::Custom(format!(
+            "Download exceeds {} MiB",
+            max_bytes / (1024 * 1024)
+        )));
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_limited() {
+        let limit = 2 * 1024 * 1024;
+        let body = vec![0; limit as usize];
+        assert_eq!(read_limited(&body[..], limit).unwrap(), body);
+
+        let body = vec![0; limit as usize + 1];
+        let err = read_limited(&body[..], limit).unwrap_err();
+        assert_eq!(err.to_string(), "Download exceeds 2 MiB");
+    }
+}
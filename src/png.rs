@@ -2,22 +2,79 @@
 //!
 //! A PNG file consists of a [PNG signature](crate::Png::STANDARD_HEADER) followed by a series of [chunks](crate::chunk::Chunk).
 
+use std::collections::HashSet;
+use std::ffi::OsString;
 use std::fmt::Display;
-use std::fs;
+use std::fs::{self, File, OpenOptions, Permissions};
+use std::io::{self, BufWriter, Read, Write};
 use std::path::Path;
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
 
-pub use crate::{chunk::Chunk, chunk_type::ChunkType};
+pub use crate::chunk::Chunk;
 
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::chunk_type::ChunkType;
 use crate::u8_4_from_slice;
 use crate::{Error, Result, CHUNK_SIZE};
 
 /// Header Size of png file
 const HEADER_SIZE: usize = 8;
 
+/// Numbers the temporary files made by [`Png::write_file`] so no two calls share one
+static TMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Data length of a complete `IHDR` chunk
+const IHDR_LEN: usize = 13;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 /// PNG file structure
 pub struct Png {
     chunks: Vec<Chunk>,
+    /// Bytes found after `IEND` that don't form a chunk, written back as they are
+    trailing: Vec<u8>,
+}
+
+/// A structural problem reported by [`Png::lint`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintIssue {
+    /// Position of the offending chunk, `None` when the problem concerns the whole file
+    pub index: Option<usize>,
+    /// Description of the problem
+    pub message: String,
+}
+
+/// A chunk found by [`Png::decode`], with its position and its data as text when possible
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodeResult {
+    /// Type of the chunk
+    pub chunk_type: ChunkType,
+    /// Raw data of the chunk
+    pub data: Vec<u8>,
+    /// The data as text, `None` if it is not valid UTF-8
+    pub as_text: Option<String>,
+    /// Position of the chunk in the file
+    pub index: usize,
+}
+
+impl LintIssue {
+    fn file(message: &str) -> Self {
+        LintIssue {
+            index: None,
+            message: message.to_owned(),
+        }
+    }
+
+    fn at(index: usize, message: &str) -> Self {
+        LintIssue {
+            index: Some(index),
+            message: message.to_owned(),
+        }
+    }
 }
 
 impl Png {
@@ -26,17 +83,190 @@ impl Png {
 
     /// Build a `Png` from chunks
     pub fn from_chunks(chunks: Vec<Chunk>) -> Png {
-        Png { chunks }
+        Png {
+            chunks,
+            trailing: Vec::new(),
+        }
+    }
+
+    /// Builds a valid black image of `width` x `height` pixels, 8-bit grayscale, to carry messages
+    /// when there is no image at hand.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a dimension is 0 or greater than 2^31 - 1, the range allowed by the PNG
+    /// specification.
+    pub fn new_minimal(width: u32, height: u32) -> Png {
+        const MAX_DIMENSION: u32 = i32::MAX as u32;
+        assert!(
+            (1..=MAX_DIMENSION).contains(&width) && (1..=MAX_DIMENSION).contains(&height),
+            "PNG dimensions must be between 1 and {}",
+            MAX_DIMENSION
+        );
+
+        let mut ihdr = Vec::with_capacity(IHDR_LEN);
+        ihdr.extend(width.to_be_bytes());
+        ihdr.extend(height.to_be_bytes());
+        // bit depth 8, grayscale, deflate, adaptive filtering, no interlace
+        ihdr.extend([8, 0, 0, 0, 0]);
+
+        // each scanline is a filter type byte (none) followed by one zero byte per pixel
+        let scanline = vec![0; width as usize + 1];
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        for _ in 0..height {
+            encoder
+                .write_all(&scanline)
+                .expect("writing to a Vec cannot fail");
+        }
+        let idat = encoder.finish().expect("writing to a Vec cannot fail");
+
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_bytes_unchecked(*b"IHDR"), ihdr),
+            Chunk::new(ChunkType::from_bytes_unchecked(*b"IDAT"), idat),
+            Chunk::new(ChunkType::from_bytes_unchecked(*b"IEND"), Vec::new()),
+        ])
+    }
+
+    /// Creates a `Png` from bytes already in memory, such as a downloaded file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Png> {
+        Png::parse(bytes, true)
+    }
+
+    /// Creates one `Png` per image of several PNG files concatenated together.
+    ///
+    /// Each image runs from its signature up to the next signature found after its chunks, or
+    /// the end of `bytes`, and is parsed on its own.
+    pub fn from_bytes_multi(bytes: &[u8]) -> Result<Vec<Png>> {
+        Png::parse_multi(bytes, true)
     }
 
     /// Creates a `Png` from a file path
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Png> {
-        let file = fs::read(path)?;
+        Png::from_bytes(&fs::read(path)?)
+    }
+
+    /// Creates a `Png` from a file path by memory-mapping it, so the whole file is never copied
+    /// to the heap at once. Only chunk data is copied while parsing.
+    #[cfg(feature = "mmap")]
+    pub fn from_file_mmap<P: AsRef<Path>>(path: P) -> Result<Png> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is only read while parsing and dropped right after.
+        // Another process truncating the file meanwhile is outside what we can guard against.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        map[..].try_into()
+    }
+
+    /// Creates a `Png` from a file path, refusing files larger than `max_bytes`
+    pub fn from_file_with_limit<P: AsRef<Path>>(path: P, max_bytes: u64) -> Result<Png> {
+        let mut file = Vec::new();
+        // read one byte past the limit to detect oversized files without trusting metadata
+        File::open(path)?
+            .take(max_bytes.saturating_add(1))
+            .read_to_end(&mut file)?;
+
+        if file.len() as u64 > max_bytes {
+            return Err(Error::Custom(format!(
+                "File exceeds the limit of {} bytes",
+                max_bytes
+            )));
+        }
+
         file.as_slice().try_into()
     }
 
+    /// Writes this `Png` to a file, streaming chunk by chunk through a buffer.
+    ///
+    /// The data goes to a temporary file in the same directory which is then renamed over `path`,
+    /// so a crash mid-write leaves either the old or the new file, never a truncated one.
+    /// A symbolic link is followed so the file it points to is replaced, and the permissions of
+    /// the replaced file are kept.
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write_file_with(path.as_ref(), false)
+    }
+
+    /// Writes this `Png` to a file like [`Png::write_file`], keeping the modification time of
+    /// the file it replaces
+    pub fn write_file_keeping_mtime<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write_file_with(path.as_ref(), true)
+    }
+
+    fn write_file_with(&self, path: &Path, keep_mtime: bool) -> Result<()> {
+        let path = match fs::canonicalize(path) {
+            Ok(real_path) => real_path,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => path.to_path_buf(),
+            Err(e) => return Err(e.into()),
+        };
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| Error::Custom(format!("{} is not a file path", path.display())))?;
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => Some(metadata),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let permissions = metadata.as_ref().map(|metadata| metadata.permissions());
+        let mtime = match &metadata {
+            Some(metadata) if keep_mtime => Some(metadata.modified()?),
+            _ => None,
+        };
+
+        // the counter keeps threads of the same process writing the same file apart
+        let mut tmp_name = OsString::from(".");
+        tmp_name.push(file_name);
+        tmp_name.push(format!(
+            ".pngchat-{}-{}.tmp",
+            process::id(),
+            TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp_path = path.with_file_name(tmp_name);
+
+        let written = self
+            .write_synced(&tmp_path, permissions, mtime)
+            .and_then(|_| {
+                fs::rename(&tmp_path, &path)?;
+                Ok(())
+            });
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        written
+    }
+
+    /// Writes and flushes this `Png` to a new file at `path`, given `permissions` before any
+    /// data is written and `mtime` once it is all written
+    fn write_synced(
+        &self,
+        path: &Path,
+        permissions: Option<Permissions>,
+        mtime: Option<SystemTime>,
+    ) -> Result<()> {
+        let file = OpenOptions::new().write(true).create_new(true).open(path)?;
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+
+        let mut writer = BufWriter::new(file);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        if let Some(mtime) = mtime {
+            writer.get_ref().set_modified(mtime)?;
+        }
+        writer.get_ref().sync_all()?;
+        Ok(())
+    }
+
+    #[deprecated(note = "use `write_file` instead")]
     pub fn wrtie_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        fs::write(path, self.as_bytes())?;
+        self.write_file(path)
+    }
+
+    /// Writes the signature and every chunk to `writer` without building the whole file in memory
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&Png::STANDARD_HEADER)?;
+        for chunk in self.chunks() {
+            chunk.write_to(writer)?;
+        }
+        writer.write_all(&self.trailing)?;
         Ok(())
     }
 
@@ -45,22 +275,142 @@ impl Png {
         self.chunks.push(chunk);
     }
 
+    /// Inserts a chunk at the front of the file, right after `IHDR` if there is one
+    pub fn prepend_chunk(&mut self, chunk: Chunk) {
+        let index = match self.chunks.first() {
+            Some(first) if has_type(first, "IHDR") => 1,
+            _ => 0,
+        };
+        self.chunks.insert(index, chunk);
+    }
+
+    /// Inserts a chunk at position `index`, shifting all chunks after it
+    pub fn insert_chunk(&mut self, index: usize, chunk: Chunk) -> Result<()> {
+        if index > self.chunks.len() {
+            return Err(Error::Custom(format!(
+                "Index {} out of range for {} chunks",
+                index,
+                self.chunks.len()
+            )));
+        }
+
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+
+    /// Inserts several chunks at position `index` in one go, keeping their order
+    pub fn insert_chunks(&mut self, index: usize, chunks: Vec<Chunk>) -> Result<()> {
+        if index > self.chunks.len() {
+            return Err(Error::Custom(format!(
+                "Index {} out of range for {} chunks",
+                index,
+                self.chunks.len()
+            )));
+        }
+
+        self.chunks.splice(index..index, chunks);
+        Ok(())
+    }
+
+    /// Moves the chunk at position `from` so that it ends up at position `to`,
+    /// shifting the chunks in between
+    pub fn move_chunk(&mut self, from: usize, to: usize) -> Result<()> {
+        if from.max(to) >= self.chunks.len() {
+            return Err(Error::Custom(format!(
+                "Index {} out of range for {} chunks",
+                from.max(to),
+                self.chunks.len()
+            )));
+        }
+
+        let chunk = self.chunks.remove(from);
+        self.chunks.insert(to, chunk);
+        Ok(())
+    }
+
     /// Searches for a `Chunk` with the specified `chunk_type` and removes the first
     /// matching `Chunk` from this `Png` list of chunks.
     pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk> {
         let removed_idx = self
             .chunks
             .iter()
-            .position(|chunk| chunk.chunk_type().to_string() == chunk_type);
+            .position(|chunk| has_type(chunk, chunk_type));
 
         match removed_idx {
             Some(idx) => Ok(self.chunks.remove(idx)),
-            None => Err(Error::Custom(
-                "Can't find certain chunk type to remove".to_owned(),
-            )),
+            None => Err(Error::ChunkNotFound(chunk_type.to_owned())),
+        }
+    }
+
+    /// Removes the chunk at position `index`, as listed by `positions_by_type`
+    pub fn remove_chunk_at(&mut self, index: usize) -> Result<Chunk> {
+        if index >= self.chunks.len() {
+            return Err(Error::Custom(format!(
+                "Index {} out of range for {} chunks",
+                index,
+                self.chunks.len()
+            )));
+        }
+        Ok(self.chunks.remove(index))
+    }
+
+    /// Adds a standard `tEXt` chunk holding `keyword` and `text`, right before `IEND` where
+    /// readers look for metadata. See [`Chunk::text`] for the accepted keywords and text.
+    pub fn append_text(&mut self, keyword: &str, text: &str) -> Result<()> {
+        let chunk = Chunk::text(keyword, text)?;
+        self.chunks.insert(self.apng_safe_index(), chunk);
+        Ok(())
+    }
+
+    /// Replaces the first chunk of the same type as `chunk`, or appends `chunk` if there is none.
+    /// Returns the replaced chunk.
+    pub fn upsert_chunk(&mut self, chunk: Chunk) -> Option<Chunk> {
+        match self
+            .chunks
+            .iter_mut()
+            .find(|existing| existing.chunk_type() == chunk.chunk_type())
+        {
+            Some(existing) => Some(std::mem::replace(existing, chunk)),
+            None => {
+                self.append_chunk(chunk);
+                None
+            }
         }
     }
 
+    /// Removes ancillary chunks identical to an earlier one (same type, data and CRC), keeping
+    /// the first. Critical chunks are never removed, as repeated `IDAT` chunks are valid image
+    /// data. Returns how many chunks were removed.
+    pub fn dedup_chunks(&mut self) -> usize {
+        let before = self.chunks.len();
+        let mut seen = HashSet::new();
+        let keep: Vec<bool> = self
+            .chunks
+            .iter()
+            .map(|chunk| {
+                chunk.chunk_type().is_critical()
+                    || seen.insert((chunk.chunk_type().bytes(), chunk.data(), chunk.crc()))
+            })
+            .collect();
+
+        let mut keep = keep.into_iter();
+        self.chunks.retain(|_| keep.next().unwrap_or(true));
+        before - self.chunks.len()
+    }
+
+    /// Keeps only the chunks for which `keep` returns true, preserving their order
+    pub fn retain_chunks<F>(&mut self, keep: F)
+    where
+        F: FnMut(&Chunk) -> bool,
+    {
+        self.chunks.retain(keep);
+    }
+
+    /// Bytes appended after `IEND` by some tools that are not chunks, empty if there are none
+    pub fn trailing_data(&self) -> &[u8] {
+        &self.trailing
+    }
+
     /// Return png signature
     pub fn header(&self) -> &[u8; 8] {
         &Png::STANDARD_HEADER
@@ -71,46 +421,449 @@ impl Png {
         &self.chunks
     }
 
+    /// Returns the chunk at position `index`, if any
+    pub fn chunk_at(&self, index: usize) -> Option<&Chunk> {
+        self.chunks.get(index)
+    }
+
+    /// Number of chunks in this `Png`
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns true if this `Png` has no chunks
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
     /// Searches for a `Chunk` with the specified `chunk_type` and returns the first
     /// matching `Chunk` from this `Png`.
     pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
         self.chunks()
             .iter()
-            .find(|chunk| chunk.chunk_type().to_string() == chunk_type)
+            .find(|chunk| has_type(chunk, chunk_type))
+    }
+
+    /// Every `Chunk` with the specified `chunk_type` with its position and data, in file order.
+    /// Fails if `chunk_type` is not a valid type code.
+    pub fn decode(&self, chunk_type: &str) -> Result<Vec<DecodeResult>> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        Ok(self
+            .chunks()
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| *chunk.chunk_type() == chunk_type)
+            .map(|(index, chunk)| DecodeResult {
+                chunk_type,
+                data: chunk.data().to_vec(),
+                as_text: chunk.data_as_string().ok(),
+                index,
+            })
+            .collect())
+    }
+
+    /// A copy of the data of the first `Chunk` with the specified `chunk_type`
+    pub fn message_bytes(&self, chunk_type: &str) -> Option<Vec<u8>> {
+        self.chunk_by_type(chunk_type)
+            .map(|chunk| chunk.data().to_vec())
+    }
+
+    /// Mutable counterpart of `chunk_by_type`, for editing a chunk in place.
+    /// Call `Chunk::refresh` after changing its data so the length and CRC stay valid.
+    pub fn chunk_by_type_mut(&mut self, chunk_type: &str) -> Option<&mut Chunk> {
+        self.chunks
+            .iter_mut()
+            .find(|chunk| has_type(chunk, chunk_type))
+    }
+
+    /// Returns the chunks placed before the first `IDAT`, which most viewers reliably preserve.
+    /// All chunks are returned if there is no `IDAT`.
+    pub fn chunks_before_idat(&self) -> &[Chunk] {
+        let first_idat = self
+            .chunks
+            .iter()
+            .position(|chunk| has_type(chunk, "IDAT"))
+            .unwrap_or(self.len());
+        &self.chunks[..first_idat]
+    }
+
+    /// Returns true if this is an animated PNG, i.e. it contains an `acTL` chunk
+    pub fn is_apng(&self) -> bool {
+        self.chunks
+            .iter()
+            .any(|chunk| chunk.chunk_type().bytes() == *b"acTL")
+    }
+
+    /// Returns true if inserting a chunk at `index` would land inside the image data,
+    /// i.e. between the first and the last `IDAT`, `fcTL` or `fdAT` chunk.
+    ///
+    /// Such an insertion breaks the consecutive `IDAT` rule and the APNG frame sequence.
+    pub fn splits_image_data(&self, index: usize) -> bool {
+        let is_image_data = |chunk: &Chunk| {
+            let chunk_type = chunk.chunk_type();
+            chunk_type.bytes() == *b"IDAT"
+                || (chunk_type.is_animation() && chunk_type.bytes() != *b"acTL")
+        };
+
+        match (
+            self.chunks.iter().position(is_image_data),
+            self.chunks.iter().rposition(is_image_data),
+        ) {
+            (Some(first), Some(last)) => first < index && index <= last,
+            _ => false,
+        }
+    }
+
+    /// Position where a new chunk can be inserted without breaking the (APNG) image data:
+    /// right before `IEND`, or at the end if there is no `IEND`.
+    pub fn apng_safe_index(&self) -> usize {
+        self.chunks
+            .iter()
+            .position(|chunk| chunk.chunk_type().bytes() == *b"IEND")
+            .unwrap_or(self.len())
+    }
+
+    /// Returns true if a `Chunk` with the specified `chunk_type` exists
+    pub fn contains_chunk_type(&self, chunk_type: &str) -> bool {
+        self.chunk_by_type(chunk_type).is_some()
+    }
+
+    /// Returns every `Chunk` with the specified `chunk_type`, in file order
+    pub fn chunks_by_type(&self, chunk_type: &str) -> Vec<&Chunk> {
+        self.chunks()
+            .iter()
+            .filter(|chunk| has_type(chunk, chunk_type))
+            .collect()
+    }
+
+    /// Indices of every `Chunk` with the specified `chunk_type`, for the index based methods
+    /// such as `move_chunk`
+    pub fn positions_by_type(&self, chunk_type: &str) -> Vec<usize> {
+        self.chunks()
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| has_type(chunk, chunk_type))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Lazy version of `chunks_by_type`, for counting or stopping early without allocating
+    pub fn iter_by_type<'a>(&'a self, chunk_type: &'a str) -> impl Iterator<Item = &'a Chunk> {
+        self.chunks()
+            .iter()
+            .filter(move |chunk| has_type(chunk, chunk_type))
+    }
+
+    /// Iterates over the chunks from the last one (usually `IEND`) back to the first, which finds
+    /// recently appended message chunks without scanning the whole file
+    pub fn iter_chunks_rev(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks().iter().rev()
+    }
+
+    /// The data of every `IDAT` chunk concatenated in file order: the still compressed image data
+    pub fn idat_stream(&self) -> Vec<u8> {
+        self.iter_by_type("IDAT")
+            .flat_map(|chunk| chunk.data())
+            .copied()
+            .collect()
+    }
+
+    /// Returns true if both files have the same critical chunks (`IHDR`, `PLTE`, `IDAT`, `IEND`)
+    /// in the same order, that is the same picture whatever ancillary chunks they hold
+    pub fn same_image_data(&self, other: &Png) -> bool {
+        let is_critical = |chunk: &&Chunk| chunk.chunk_type().is_critical();
+        self.chunks
+            .iter()
+            .filter(is_critical)
+            .eq(other.chunks.iter().filter(is_critical))
+    }
+
+    /// Moves every chunk found after `IEND` in front of it, so that `IEND` ends the file.
+    ///
+    /// The relative order of all other chunks (and thus of the critical chunks) is preserved,
+    /// which makes this operation idempotent. A `Png` without `IEND` is left untouched.
+    pub fn normalize(&mut self) {
+        let (mut chunks, iends): (Vec<Chunk>, Vec<Chunk>) = self
+            .chunks
+            .drain(..)
+            .partition(|chunk| chunk.chunk_type().bytes() != *b"IEND");
+
+        chunks.extend(iends);
+        self.chunks = chunks;
+    }
+
+    /// Width and height in pixels from `IHDR`, `None` if it is missing or too short
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        let ihdr = self.ihdr_data()?;
+        let width = u32::from_be_bytes(u8_4_from_slice(&ihdr[0..4]));
+        let height = u32::from_be_bytes(u8_4_from_slice(&ihdr[4..8]));
+        Some((width, height))
+    }
+
+    /// Bits per sample or palette index from `IHDR`, `None` if it is missing or too short
+    pub fn bit_depth(&self) -> Option<u8> {
+        self.ihdr_data().map(|ihdr| ihdr[8])
+    }
+
+    /// Color type code from `IHDR` (0, 2, 3, 4 or 6 in a valid file), `None` if it is missing
+    /// or too short
+    pub fn color_type(&self) -> Option<u8> {
+        self.ihdr_data().map(|ihdr| ihdr[9])
+    }
+
+    /// Data of the `IHDR` chunk, if it holds the 13 bytes of a complete header
+    fn ihdr_data(&self) -> Option<&[u8]> {
+        self.chunk_by_type("IHDR")
+            .map(Chunk::data)
+            .filter(|data| data.len() >= IHDR_LEN)
+    }
+
+    /// Parses the `PLTE` chunk into its RGB entries, returns `None` if there is no palette
+    pub fn palette(&self) -> Result<Option<Vec<(u8, u8, u8)>>> {
+        let plte = match self.chunk_by_type("PLTE") {
+            Some(chunk) => chunk,
+            None => return Ok(None),
+        };
+
+        if plte.data().len() % 3 != 0 {
+            return Err(Error::Custom(
+                "PLTE chunk length is not a multiple of 3".to_owned(),
+            ));
+        }
+
+        Ok(Some(
+            plte.data()
+                .chunks_exact(3)
+                .map(|rgb| (rgb[0], rgb[1], rgb[2]))
+                .collect(),
+        ))
+    }
+
+    /// Returns the indices of the chunks whose stored CRC does not match their content
+    pub fn validate_all_crcs(&self) -> Vec<usize> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| !chunk.crc_matches(chunk.crc()))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Checks the chunk layout against the PNG specification and reports every problem found,
+    /// file-level problems first and then in chunk order.
+    pub fn lint(&self) -> Vec<LintIssue> {
+        let mut issues = Vec::new();
+        let positions = |chunk_type: &str| -> Vec<usize> {
+            self.chunks
+                .iter()
+                .enumerate()
+                .filter(|(_, chunk)| has_type(chunk, chunk_type))
+                .map(|(i, _)| i)
+                .collect()
+        };
+
+        let ihdrs = positions("IHDR");
+        match ihdrs.first() {
+            None => issues.push(LintIssue::file("Missing IHDR chunk")),
+            Some(0) => {}
+            Some(&i) => issues.push(LintIssue::at(i, "IHDR must be the first chunk")),
+        }
+        for &i in ihdrs.iter().skip(1) {
+            issues.push(LintIssue::at(i, "Duplicate IHDR chunk"));
+        }
+
+        let idats = positions("IDAT");
+        match (idats.first(), ihdrs.first()) {
+            (None, _) => issues.push(LintIssue::file("No IDAT chunk, the image has no data")),
+            (Some(&idat), Some(&ihdr)) if idat < ihdr => {
+                issues.push(LintIssue::at(idat, "IDAT placed before IHDR"))
+            }
+            _ => {}
+        }
+        if let Some(gap) = idats.windows(2).find(|pair| pair[1] != pair[0] + 1) {
+            issues.push(LintIssue::at(gap[1], "IDAT chunks are not consecutive"));
+        }
+
+        match positions("IEND").first() {
+            None => issues.push(LintIssue::file("Missing IEND chunk")),
+            Some(&iend) => {
+                for i in iend + 1..self.len() {
+                    issues.push(LintIssue::at(i, "Chunk placed after IEND"));
+                }
+            }
+        }
+
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            if !chunk.chunk_type().is_reserved_bit_valid() {
+                issues.push(LintIssue::at(i, "Chunk type has the reserved bit set"));
+            }
+        }
+        for i in self.validate_all_crcs() {
+            issues.push(LintIssue::at(
+                i,
+                "Stored CRC does not match the chunk content",
+            ));
+        }
+
+        issues.sort_by_key(|issue| issue.index);
+        issues
     }
 
     /// Bytes representation for Png
     pub fn as_bytes(&self) -> Vec<u8> {
-        [
-            Png::STANDARD_HEADER.to_vec(),
-            self.chunks()
-                .iter()
-                .flat_map(|chunk| chunk.as_bytes())
-                .collect(),
-        ]
-        .concat()
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)
+            .expect("writing to a Vec never fails");
+        bytes
     }
 }
 
-impl TryFrom<&[u8]> for Png {
-    type Error = Error;
+/// Exact match of a chunk type against a type code, comparing bytes without allocating
+fn has_type(chunk: &Chunk, chunk_type: &str) -> bool {
+    chunk.chunk_type().bytes() == chunk_type.as_bytes()
+}
 
-    fn try_from(bytes: &[u8]) -> Result<Self> {
-        if bytes[0..HEADER_SIZE] != Png::STANDARD_HEADER {
+impl Png {
+    /// Parses a `Png` like [`TryFrom`] does, but keeps chunks whose stored CRC is wrong.
+    ///
+    /// Use [`Png::validate_all_crcs`] on the result to find the damaged chunks.
+    pub fn from_bytes_unchecked(bytes: &[u8]) -> Result<Png> {
+        Png::parse(bytes, false)
+    }
+
+    /// Splits concatenated PNG files like [`Png::from_bytes_multi`] without enforcing chunk CRCs
+    pub fn from_bytes_multi_unchecked(bytes: &[u8]) -> Result<Vec<Png>> {
+        Png::parse_multi(bytes, false)
+    }
+
+    /// Creates a `Png` from a file path without enforcing chunk CRCs
+    pub fn from_file_unchecked<P: AsRef<Path>>(path: P) -> Result<Png> {
+        Png::from_bytes_unchecked(&fs::read(path)?)
+    }
+
+    /// Parses the chunks following the signature.
+    ///
+    /// Once `IEND` was read, bytes that can't be a chunk at all end the chunk list and are
+    /// kept as trailing data instead of failing. A chunk that frames correctly but is damaged,
+    /// such as one with a wrong CRC, is still an error.
+    fn parse(bytes: &[u8], check_crc: bool) -> Result<Png> {
+        if bytes.len() < HEADER_SIZE || bytes[0..HEADER_SIZE] != Png::STANDARD_HEADER {
             return Err(Error::Custom("Invalid PNG signature".to_owned()));
         }
 
         let mut cur = HEADER_SIZE; // current position of comsuming btyes
         let mut chunks: Vec<Chunk> = Vec::new();
+        let mut after_iend = false;
 
         while cur < bytes.len() {
+            let chunk = match Png::parse_chunk(&bytes[cur..], check_crc) {
+                Ok(chunk) => chunk,
+                Err(_) if after_iend && !Chunk::is_framed(&bytes[cur..]) => {
+                    let mut png = Png::from_chunks(chunks);
+                    png.trailing = bytes[cur..].to_vec();
+                    return Ok(png);
+                }
+                Err(e) => return Err(e),
+            };
+
+            cur += chunk.length() as usize + 3 * CHUNK_SIZE;
+            after_iend |= chunk.chunk_type().bytes() == *b"IEND";
+            chunks.push(chunk);
+        }
+
+        Ok(Png::from_chunks(chunks))
+    }
+
+    /// Parses every image of concatenated PNG files, the first one starting at offset 0
+    fn parse_multi(bytes: &[u8], check_crc: bool) -> Result<Vec<Png>> {
+        let mut pngs = Vec::new();
+        let mut start = 0;
+
+        loop {
+            let image = &bytes[start..];
+            // only look for the next signature past the chunks, as chunk data may hold a PNG
+            let chunks_end = Png::chunks_len(image);
+            let end = image[chunks_end..]
+                .windows(HEADER_SIZE)
+                .position(|window| window == Png::STANDARD_HEADER)
+                .map_or(image.len(), |offset| chunks_end + offset);
+
+            pngs.push(Png::parse(&image[..end], check_crc)?);
+            if end == image.len() {
+                return Ok(pngs);
+            }
+            start += end;
+        }
+    }
+
+    /// Number of bytes taken by the signature and the chunks up to `IEND`, following only the
+    /// length fields. Stops early at a length running past the end of `bytes`.
+    fn chunks_len(bytes: &[u8]) -> usize {
+        let mut cur = HEADER_SIZE.min(bytes.len());
+
+        while bytes.len() - cur >= 3 * CHUNK_SIZE {
             let length = u32::from_be_bytes(u8_4_from_slice(&bytes[cur..cur + CHUNK_SIZE]));
             let offset = length as usize + 3 * CHUNK_SIZE;
-            chunks.push(bytes[cur..cur + offset].try_into()?);
+            if offset > bytes.len() - cur {
+                break;
+            }
+
+            let chunk_type = &bytes[cur + CHUNK_SIZE..cur + 2 * CHUNK_SIZE];
             cur += offset;
+            if chunk_type == b"IEND" {
+                break;
+            }
         }
 
-        Ok(Png::from_chunks(chunks))
+        cur
+    }
+
+    /// Parses the chunk at the start of `bytes`, which may go on with more chunks
+    fn parse_chunk(bytes: &[u8], check_crc: bool) -> Result<Chunk> {
+        if bytes.len() < 3 * CHUNK_SIZE {
+            return Err(Error::Custom("Truncated chunk at end of file".to_owned()));
+        }
+
+        let length = u32::from_be_bytes(u8_4_from_slice(&bytes[..CHUNK_SIZE]));
+        // compare before slicing so a lying length field is rejected up front
+        let offset = length as usize + 3 * CHUNK_SIZE;
+        if offset > bytes.len() {
+            return Err(Error::Custom(format!(
+                "Chunk declares {} bytes of data but only {} bytes remain",
+                length,
+                bytes.len() - 3 * CHUNK_SIZE
+            )));
+        }
+
+        let chunk = &bytes[..offset];
+        if check_crc {
+            chunk.try_into()
+        } else {
+            Chunk::try_from_unchecked(chunk)
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for Png {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Png::parse(bytes, true)
+    }
+}
+
+impl FromIterator<Chunk> for Png {
+    fn from_iter<I: IntoIterator<Item = Chunk>>(chunks: I) -> Self {
+        Png::from_chunks(chunks.into_iter().collect())
+    }
+}
+
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.index {
+            Some(index) => write!(f, "chunk#{}: {}", index, self.message),
+            None => write!(f, "{}", self.message),
+        }
     }
 }
 
@@ -122,6 +875,9 @@ impl Display for Png {
             writeln!(f, "{}", chunk)?;
         }
         write!(f, "]")?;
+        if !self.trailing.is_empty() {
+            write!(f, "\ntrailing data: {} bytes", self.trailing.len())?;
+        }
 
         Ok(())
     }
@@ -136,13 +892,11 @@ mod tests {
     use std::str::FromStr;
 
     fn testing_chunks() -> Vec<Chunk> {
-        let mut chunks = Vec::new();
-
-        chunks.push(chunk_from_strings("FrSt", "I am the first chunk").unwrap());
-        chunks.push(chunk_from_strings("miDl", "I am another chunk").unwrap());
-        chunks.push(chunk_from_strings("LASt", "I am the last chunk").unwrap());
-
-        chunks
+        vec![
+            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
+            chunk_from_strings("miDl", "I am another chunk").unwrap(),
+            chunk_from_strings("LASt", "I am the last chunk").unwrap(),
+        ]
     }
 
     fn testing_png() -> Png {
@@ -150,6 +904,13 @@ mod tests {
         Png::from_chunks(chunks)
     }
 
+    fn chunk_types(png: &Png) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
     fn chunk_from_strings(chunk_type: &str, data: &str) -> Result<Chunk> {
         let chunk_type = ChunkType::from_str(chunk_type)?;
         let data: Vec<u8> = data.bytes().collect();
@@ -162,7 +923,16 @@ mod tests {
         let chunks = testing_chunks();
         let png = Png::from_chunks(chunks);
 
-        assert_eq!(png.chunks().len(), 3);
+        assert_eq!(png.len(), 3);
+        assert!(!png.is_empty());
+        assert!(Png::from_chunks(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let png: Png = testing_chunks().into_iter().collect();
+
+        assert_eq!(png, testing_png());
     }
 
     #[test]
@@ -223,6 +993,138 @@ mod tests {
         assert!(png.is_err());
     }
 
+    #[test]
+    fn test_validate_all_crcs() {
+        let mut bytes = testing_png().as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+
+        let png = Png::from_bytes_unchecked(&bytes).unwrap();
+        assert_eq!(png.validate_all_crcs(), vec![2]);
+        assert!(testing_png().validate_all_crcs().is_empty());
+    }
+
+    #[test]
+    fn test_from_bytes_multi() {
+        let mut first = testing_png();
+        // a whole PNG inside chunk data must not be taken for the start of the next image
+        first.append_chunk(Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            Png::new_minimal(1, 1).as_bytes(),
+        ));
+        first.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        let second = Png::new_minimal(2, 3);
+
+        let mut bytes = first.as_bytes();
+        bytes.extend(b"junk");
+        bytes.extend(second.as_bytes());
+
+        let pngs = Png::from_bytes_multi(&bytes).unwrap();
+        assert_eq!(pngs.len(), 2);
+        assert_eq!(pngs[0].chunks(), first.chunks());
+        assert_eq!(pngs[0].trailing_data(), b"junk");
+        assert_eq!(pngs[1].chunks(), second.chunks());
+        assert_eq!(pngs[1].dimensions(), Some((2, 3)));
+
+        let single = Png::from_bytes_multi(&second.as_bytes()).unwrap();
+        assert_eq!(single.len(), 1);
+    }
+
+    #[test]
+    fn test_from_bytes_multi_validates_each_image() {
+        let mut bytes = Png::new_minimal(1, 1).as_bytes();
+        let mut second = Png::new_minimal(1, 1).as_bytes();
+        // corrupt the CRC of the IHDR chunk of the second image
+        second[HEADER_SIZE + 3 * CHUNK_SIZE + IHDR_LEN - 1] ^= 0xff;
+        bytes.extend(&second);
+
+        assert!(Png::from_bytes_multi(&bytes).is_err());
+        assert_eq!(Png::from_bytes_multi_unchecked(&bytes).unwrap().len(), 2);
+        assert!(Png::from_bytes_multi(b"not a png").is_err());
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        png.append_chunk(chunk_from_strings("ruSt", "after IEND").unwrap());
+        let mut bytes = png.as_bytes();
+        assert!(Png::try_from(bytes.as_ref())
+            .unwrap()
+            .trailing_data()
+            .is_empty());
+
+        bytes.extend(b"appended by another tool");
+        let parsed = Png::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(parsed.chunks(), png.chunks());
+        assert_eq!(parsed.trailing_data(), b"appended by another tool");
+        assert_eq!(parsed.as_bytes(), bytes);
+
+        // before IEND the same bytes are a broken chunk
+        let mut bytes = testing_png().as_bytes();
+        bytes.extend(b"appended by another tool");
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_corrupted_chunk_after_iend() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        png.append_chunk(chunk_from_strings("ruSt", "after IEND").unwrap());
+        let mut bytes = png.as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+
+        // a whole chunk with a wrong CRC is damage, not trailing data
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+        let parsed = Png::from_bytes_unchecked(&bytes).unwrap();
+        assert_eq!(parsed.chunks().len(), png.chunks().len());
+        assert!(parsed.trailing_data().is_empty());
+    }
+
+    #[test]
+    fn test_lying_chunk_length() {
+        #[rustfmt::skip]
+        let bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .chain([
+                255, 255, 255, 255, // length (lying)
+                82, 117, 83, 116,   // Chunk Type
+                1, 2, 3, 4,         // CRC
+            ].iter())
+            .copied()
+            .collect();
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+        assert!(Png::try_from(&bytes[..HEADER_SIZE + 2]).is_err());
+        assert!(Png::try_from(&bytes[..4]).is_err());
+    }
+
+    #[test]
+    fn test_from_file_with_limit() {
+        let path = std::env::temp_dir().join("pngchat_test_from_file_with_limit.png");
+        fs::write(&path, PNG_FILE).unwrap();
+
+        assert!(Png::from_file_with_limit(&path, PNG_FILE.len() as u64).is_ok());
+        assert!(Png::from_file_with_limit(&path, 100).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_from_file_mmap() {
+        let path = std::env::temp_dir().join("pngchat_test_from_file_mmap.png");
+        fs::write(&path, PNG_FILE).unwrap();
+
+        let png = Png::from_file_mmap(&path).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE);
+
+        fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_list_chunks() {
         let png = testing_png();
@@ -238,6 +1140,95 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
     }
 
+    #[test]
+    fn test_decode() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("miDl").unwrap(),
+            vec![0xff, 0xfe],
+        ));
+
+        let results = png.decode("miDl").unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].index, 1);
+        assert_eq!(results[0].chunk_type.to_string(), "miDl");
+        assert_eq!(results[0].data, b"I am another chunk");
+        assert_eq!(results[0].as_text.as_deref(), Some("I am another chunk"));
+        assert_eq!(results[1].index, 3);
+        assert_eq!(results[1].as_text, None);
+
+        assert!(png.decode("ruSt").unwrap().is_empty());
+        assert!(png.decode("ru5t").is_err());
+    }
+
+    #[test]
+    fn test_message_bytes() {
+        let png = testing_png();
+        assert_eq!(png.message_bytes("miDl").unwrap(), b"I am another chunk");
+        assert_eq!(png.message_bytes("ruSt"), None);
+    }
+
+    #[test]
+    fn test_chunk_by_type_mut() {
+        let mut png = testing_png();
+        let chunk = png.chunk_by_type_mut("miDl").unwrap();
+        *chunk.data_mut() = b"I was edited".to_vec();
+        chunk.refresh();
+
+        let chunk = png.chunk_by_type("miDl").unwrap();
+        assert_eq!(&chunk.data_as_string().unwrap(), "I was edited");
+        assert!(chunk.crc_matches(chunk.crc()));
+        assert!(png.chunk_by_type_mut("NoNe").is_none());
+    }
+
+    #[test]
+    fn test_chunk_at() {
+        let png = testing_png();
+        assert_eq!(&png.chunk_at(1).unwrap().chunk_type().to_string(), "miDl");
+        assert!(png.chunk_at(3).is_none());
+    }
+
+    #[test]
+    fn test_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("FrSt", "I am another first chunk").unwrap());
+
+        let chunks = png.chunks_by_type("FrSt");
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            &chunks[1].data_as_string().unwrap(),
+            "I am another first chunk"
+        );
+        assert!(png.chunks_by_type("TeSt").is_empty());
+    }
+
+    #[test]
+    fn test_positions_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am yet another chunk").unwrap());
+        assert_eq!(png.positions_by_type("miDl"), [1, 3]);
+        assert_eq!(png.positions_by_type("FrSt"), [0]);
+        assert!(png.positions_by_type("ruSt").is_empty());
+    }
+
+    #[test]
+    fn test_iter_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("FrSt", "I am another first chunk").unwrap());
+
+        assert_eq!(png.iter_by_type("FrSt").count(), 2);
+        let last = png.iter_by_type("FrSt").last().unwrap();
+        assert_eq!(&last.data_as_string().unwrap(), "I am another first chunk");
+        assert!(png.iter_by_type("TeSt").next().is_none());
+    }
+
+    #[test]
+    fn test_contains_chunk_type() {
+        let png = testing_png();
+        assert!(png.contains_chunk_type("miDl"));
+        assert!(!png.contains_chunk_type("TeSt"));
+    }
+
     #[test]
     fn test_append_chunk() {
         let mut png = testing_png();
@@ -256,6 +1247,351 @@ mod tests {
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_remove_missing_chunk() {
+        let mut png = testing_png();
+        let err = png.remove_chunk("TeSt").unwrap_err();
+        assert!(matches!(err, Error::ChunkNotFound(ty) if ty == "TeSt"));
+    }
+
+    #[test]
+    fn test_remove_chunk_at() {
+        let mut png = testing_png();
+        let removed = png.remove_chunk_at(1).unwrap();
+        assert_eq!(removed.chunk_type().to_string(), "miDl");
+        assert_eq!(png.len(), 2);
+        assert!(png.remove_chunk_at(2).is_err());
+    }
+
+    #[test]
+    fn test_prepend_chunk() {
+        let mut png = testing_apng();
+        png.prepend_chunk(chunk_from_strings("ruSt", "front").unwrap());
+        assert_eq!(&png.chunk_at(1).unwrap().chunk_type().to_string(), "ruSt");
+
+        let mut png = testing_png();
+        png.prepend_chunk(chunk_from_strings("ruSt", "front").unwrap());
+        assert_eq!(&png.chunk_at(0).unwrap().chunk_type().to_string(), "ruSt");
+    }
+
+    #[test]
+    fn test_append_text() {
+        let mut png = Png::new_minimal(1, 1);
+        png.append_text("Title", "pngchat").unwrap();
+
+        assert_eq!(chunk_types(&png), ["IHDR", "IDAT", "tEXt", "IEND"]);
+        assert_eq!(png.chunk_at(2).unwrap().data(), b"Title\0pngchat");
+
+        assert!(png.append_text("", "no keyword").is_err());
+        assert_eq!(png.len(), 4);
+    }
+
+    #[test]
+    fn test_upsert_chunk() {
+        let mut png = testing_png();
+        let replaced = png.upsert_chunk(chunk_from_strings("miDl", "new middle").unwrap());
+        assert_eq!(
+            &replaced.unwrap().data_as_string().unwrap(),
+            "I am another chunk"
+        );
+        assert_eq!(png.len(), 3);
+        assert_eq!(
+            &png.chunk_at(1).unwrap().data_as_string().unwrap(),
+            "new middle"
+        );
+
+        assert!(png
+            .upsert_chunk(chunk_from_strings("TeSt", "Message").unwrap())
+            .is_none());
+        assert_eq!(png.len(), 4);
+        assert!(png.contains_chunk_type("TeSt"));
+    }
+
+    #[test]
+    fn test_dedup_chunks() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am another chunk").unwrap());
+        png.append_chunk(chunk_from_strings("miDl", "I am a different chunk").unwrap());
+        png.append_chunk(chunk_from_strings("miDl", "I am another chunk").unwrap());
+
+        assert_eq!(png.dedup_chunks(), 2);
+        assert_eq!(png.len(), 4);
+        assert_eq!(png.chunks_by_type("miDl").len(), 2);
+        assert_eq!(png.dedup_chunks(), 0);
+    }
+
+    #[test]
+    fn test_dedup_chunks_keeps_critical() {
+        let mut png = Png::new_minimal(1, 1);
+        let idat = png.chunk_by_type("IDAT").unwrap().clone();
+        png.insert_chunk(2, idat).unwrap();
+        let before = chunk_types(&png);
+        assert_eq!(before, ["IHDR", "IDAT", "IDAT", "IEND"]);
+
+        assert_eq!(png.dedup_chunks(), 0);
+        assert_eq!(chunk_types(&png), before);
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let png = Png::from_bytes(&PNG_FILE).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE);
+        assert!(Png::from_bytes(&PNG_FILE[1..]).is_err());
+    }
+
+    #[test]
+    fn test_lint_valid_png() {
+        let png = Png::try_from(PNG_FILE.as_ref()).unwrap();
+        assert!(png.lint().is_empty());
+    }
+
+    #[test]
+    fn test_lint_reports_every_issue() {
+        let png = Png::from_chunks(vec![
+            chunk_from_strings("IDAT", "data").unwrap(),
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("ruSt", "message").unwrap(),
+            chunk_from_strings("IDAT", "data").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+            chunk_from_strings("rust", "late").unwrap(),
+        ]);
+
+        let issues: Vec<String> = png.lint().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            issues,
+            [
+                "chunk#0: IDAT placed before IHDR",
+                "chunk#1: IHDR must be the first chunk",
+                "chunk#3: IDAT chunks are not consecutive",
+                "chunk#5: Chunk placed after IEND",
+                "chunk#5: Chunk type has the reserved bit set",
+            ]
+        );
+
+        let issues: Vec<String> = testing_png()
+            .lint()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                "Missing IHDR chunk",
+                "No IDAT chunk, the image has no data",
+                "Missing IEND chunk",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retain_chunks() {
+        let mut png = testing_png();
+        png.retain_chunks(|chunk| chunk.chunk_type().to_string() != "miDl");
+        assert_eq!(chunk_types(&png), ["FrSt", "LASt"]);
+    }
+
+    fn testing_apng() -> Png {
+        Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("acTL", "control").unwrap(),
+            chunk_from_strings("fcTL", "frame 0").unwrap(),
+            chunk_from_strings("IDAT", "data 0").unwrap(),
+            chunk_from_strings("fcTL", "frame 1").unwrap(),
+            chunk_from_strings("fdAT", "data 1").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ])
+    }
+
+    #[test]
+    fn test_insert_chunk() {
+        let mut png = testing_png();
+        png.insert_chunk(1, chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+        assert_eq!(&png.chunks()[1].chunk_type().to_string(), "TeSt");
+
+        let chunk = chunk_from_strings("TeSt", "Message").unwrap();
+        assert!(png.insert_chunk(5, chunk).is_err());
+    }
+
+    #[test]
+    fn test_move_chunk() {
+        let mut png = testing_png();
+        png.move_chunk(0, 2).unwrap();
+        assert_eq!(chunk_types(&png), ["miDl", "LASt", "FrSt"]);
+
+        png.move_chunk(2, 0).unwrap();
+        assert_eq!(png, testing_png());
+
+        assert!(png.move_chunk(3, 0).is_err());
+        assert!(png.move_chunk(0, 3).is_err());
+    }
+
+    #[test]
+    fn test_insert_chunks() {
+        let mut png = testing_png();
+        let chunks = vec![
+            chunk_from_strings("OnEe", "one").unwrap(),
+            chunk_from_strings("TwOo", "two").unwrap(),
+        ];
+        png.insert_chunks(1, chunks.clone()).unwrap();
+        assert_eq!(chunk_types(&png), ["FrSt", "OnEe", "TwOo", "miDl", "LASt"]);
+
+        assert!(png.insert_chunks(6, chunks).is_err());
+    }
+
+    #[test]
+    fn test_chunks_before_idat() {
+        let png = testing_apng();
+        let types: Vec<String> = png
+            .chunks_before_idat()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "acTL", "fcTL"]);
+
+        assert_eq!(testing_png().chunks_before_idat().len(), 3);
+    }
+
+    #[test]
+    fn test_iter_chunks_rev() {
+        let png = testing_png();
+        let types: Vec<String> = png
+            .iter_chunks_rev()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["LASt", "miDl", "FrSt"]);
+
+        let last = png
+            .iter_chunks_rev()
+            .find(|chunk| !chunk.chunk_type().is_critical())
+            .unwrap();
+        assert_eq!(last.chunk_type().to_string(), "miDl");
+    }
+
+    #[test]
+    fn test_header_fields() {
+        let mut ihdr = Vec::new();
+        ihdr.extend(640u32.to_be_bytes());
+        ihdr.extend(480u32.to_be_bytes());
+        ihdr.extend([8, 6, 0, 0, 0]);
+        let mut png = testing_png();
+        png.prepend_chunk(Chunk::new(ChunkType::from_str("IHDR").unwrap(), ihdr));
+
+        assert_eq!(png.dimensions(), Some((640, 480)));
+        assert_eq!(png.bit_depth(), Some(8));
+        assert_eq!(png.color_type(), Some(6));
+
+        let png = testing_png();
+        assert_eq!(png.dimensions(), None);
+        assert_eq!(png.bit_depth(), None);
+        assert_eq!(png.color_type(), None);
+
+        let png = Png::from_chunks(vec![chunk_from_strings("IHDR", "header").unwrap()]);
+        assert_eq!(png.dimensions(), None);
+    }
+
+    #[test]
+    fn test_new_minimal() {
+        let png = Png::new_minimal(3, 2);
+        assert!(png.lint().is_empty());
+        assert_eq!(png.dimensions(), Some((3, 2)));
+        assert_eq!(png.bit_depth(), Some(8));
+        assert_eq!(png.color_type(), Some(0));
+
+        let parsed = Png::from_bytes(&png.as_bytes()).unwrap();
+        assert_eq!(parsed, png);
+
+        let mut pixels = Vec::new();
+        flate2::read::ZlibDecoder::new(png.idat_stream().as_slice())
+            .read_to_end(&mut pixels)
+            .unwrap();
+        assert_eq!(pixels, vec![0; 2 * (3 + 1)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_minimal_empty() {
+        Png::new_minimal(0, 10);
+    }
+
+    #[test]
+    fn test_same_image_data() {
+        let original = Png::new_minimal(2, 2);
+
+        let mut encoded = original.clone();
+        encoded.prepend_chunk(chunk_from_strings("ruSt", "hidden").unwrap());
+        encoded.append_chunk(chunk_from_strings("ruSt", "after IEND").unwrap());
+        assert!(encoded.same_image_data(&original));
+
+        encoded.retain_chunks(|chunk| chunk.chunk_type().to_string() != "ruSt");
+        assert_eq!(encoded, original);
+
+        assert!(!Png::new_minimal(2, 3).same_image_data(&original));
+        let mut reordered = original.clone();
+        reordered.move_chunk(1, 2).unwrap();
+        assert!(!reordered.same_image_data(&original));
+    }
+
+    #[test]
+    fn test_idat_stream() {
+        let mut png = testing_png();
+        assert!(png.idat_stream().is_empty());
+
+        png.insert_chunk(1, chunk_from_strings("IDAT", "abc").unwrap())
+            .unwrap();
+        png.append_chunk(chunk_from_strings("IDAT", "").unwrap());
+        png.append_chunk(chunk_from_strings("IDAT", "def").unwrap());
+        assert_eq!(png.idat_stream(), b"abcdef");
+    }
+
+    #[test]
+    fn test_apng_placement() {
+        let png = testing_apng();
+        assert!(png.is_apng());
+        assert!(!testing_png().is_apng());
+
+        assert_eq!(png.apng_safe_index(), 6);
+        assert!(!png.splits_image_data(png.apng_safe_index()));
+        assert!(!png.splits_image_data(2));
+        assert!(png.splits_image_data(3));
+        assert!(png.splits_image_data(5));
+    }
+
+    #[test]
+    fn test_palette() {
+        let mut png = testing_png();
+        assert!(png.palette().unwrap().is_none());
+
+        let plte = Chunk::new(
+            ChunkType::from_str("PLTE").unwrap(),
+            vec![255, 0, 0, 0, 0, 255],
+        );
+        png.append_chunk(plte);
+        assert_eq!(
+            png.palette().unwrap().unwrap(),
+            vec![(255, 0, 0), (0, 0, 255)]
+        );
+
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::from_str("PLTE").unwrap(), vec![1, 2]));
+        assert!(png.palette().is_err());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        png.append_chunk(chunk_from_strings("ruSt", "After the end").unwrap());
+
+        png.normalize();
+
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt", "ruSt", "IEND"]);
+
+        let normalized = png.clone();
+        png.normalize();
+        assert_eq!(png, normalized);
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = Png::try_from(&PNG_FILE[..]);
@@ -266,10 +1602,112 @@ mod tests {
     fn test_as_bytes() {
         let png = Png::try_from(&PNG_FILE[..]).unwrap();
         let actual = png.as_bytes();
-        let expected: Vec<u8> = PNG_FILE.iter().copied().collect();
+        let expected: Vec<u8> = PNG_FILE.to_vec();
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_write_file() {
+        let dir = std::env::temp_dir().join("pngchat_test_write_file");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.png");
+        fs::write(&path, b"old content").unwrap();
+
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.write_file(&path).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), PNG_FILE);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_file_keeps_permissions_and_follows_symlinks() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = std::env::temp_dir().join("pngchat_test_write_file_unix");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("secret.png");
+        let link = dir.join("link.png");
+        fs::write(&path, b"old content").unwrap();
+        fs::set_permissions(&path, Permissions::from_mode(0o600)).unwrap();
+        symlink(&path, &link).unwrap();
+
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.write_file(&link).unwrap();
+
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read(&path).unwrap(), PNG_FILE);
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_file_keeping_mtime_of_read_only_file() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::Duration;
+
+        let path = std::env::temp_dir().join("pngchat_test_write_file_keeping_mtime.png");
+        let _ = fs::remove_file(&path);
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.write_file(&path).unwrap();
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        fs::set_permissions(&path, Permissions::from_mode(0o444)).unwrap();
+
+        png.write_file_keeping_mtime(&path).unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        assert_eq!(metadata.modified().unwrap(), old);
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o444);
+
+        png.write_file(&path).unwrap();
+        assert_ne!(fs::metadata(&path).unwrap().modified().unwrap(), old);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_file_from_threads() {
+        let dir = std::env::temp_dir().join("pngchat_test_write_file_threads");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.png");
+
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| png.write_file(&path).unwrap());
+            }
+        });
+
+        assert_eq!(fs::read(&path).unwrap(), PNG_FILE);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_to() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut written = Vec::new();
+        png.write_to(&mut written).unwrap();
+        assert_eq!(written, png.as_bytes());
+    }
+
     #[test]
     fn test_png_trait_impls() {
         let chunk_bytes: Vec<u8> = testing_chunks()
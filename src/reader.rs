@@ -0,0 +1,278 @@
+//! Streaming access to the chunks of a PNG file
+//!
+//! [`ChunkReader`] reads one [chunk](crate::chunk::Chunk) at a time from any [`Read`] source,
+//! so listing the chunks of a huge file only needs memory for the largest chunk.
+
+use std::io::{self, Read};
+
+use crate::chunk::Chunk;
+use crate::png::Png;
+use crate::{Error, Result, CHUNK_SIZE};
+
+/// Iterator yielding the chunks of a PNG stream one by one
+pub struct ChunkReader<R: Read> {
+    reader: R,
+    /// Whether chunks with a wrong CRC are reported as errors
+    check_crc: bool,
+    /// Whether chunk type codes that are not ASCII letters are reported as errors
+    check_type: bool,
+    /// Set once the end of the stream or an error has been reached
+    done: bool,
+    /// Set once `IEND` has been read, from then on bytes that don't form a chunk are
+    /// trailing data rather than an error
+    after_iend: bool,
+    /// Number of bytes read after the signature
+    position: u64,
+    /// Number of bytes found after the last chunk
+    trailing_len: u64,
+}
+
+impl<R: Read> ChunkReader<R> {
+    /// Creates a `ChunkReader` after consuming and checking the PNG signature
+    pub fn new(mut reader: R) -> Result<ChunkReader<R>> {
+        let mut header = [0; Png::STANDARD_HEADER.len()];
+        reader.read_exact(&mut header)?;
+
+        if header != Png::STANDARD_HEADER {
+            return Err(Error::Custom("Invalid PNG signature".to_owned()));
+        }
+
+        Ok(ChunkReader {
+            reader,
+            check_crc: true,
+            check_type: true,
+            done: false,
+            after_iend: false,
+            position: 0,
+            trailing_len: 0,
+        })
+    }
+
+    /// Sets whether chunks with a wrong CRC are errors (the default) or yielded as they are
+    pub fn check_crc(mut self, check_crc: bool) -> Self {
+        self.check_crc = check_crc;
+        self
+    }
+
+    /// Sets whether invalid chunk type codes are errors (the default) or yielded as they are
+    pub fn check_type(mut self, check_type: bool) -> Self {
+        self.check_type = check_type;
+        self
+    }
+
+    /// Number of bytes after `IEND` that don't form a chunk, known once the iteration ended
+    pub fn trailing_data_len(&self) -> u64 {
+        self.trailing_len
+    }
+
+    /// Reads the length field of the next chunk, returns `None` on a clean end of stream
+    fn read_length(&mut self) -> Result<Option<[u8; CHUNK_SIZE]>> {
+        let mut length = [0; CHUNK_SIZE];
+        let mut filled = 0;
+
+        while filled < CHUNK_SIZE {
+            match self.reader.read(&mut length[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(n) => {
+                    filled += n;
+                    self.position += n as u64;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(Some(length))
+    }
+
+    /// Reads the type, data and CRC following an already read length field, returned along
+    /// with the length field
+    fn read_chunk_bytes(&mut self, length: [u8; CHUNK_SIZE]) -> Result<Vec<u8>> {
+        let remaining = u32::from_be_bytes(length) as u64 + 2 * CHUNK_SIZE as u64;
+
+        let mut bytes = length.to_vec();
+        // `take` keeps a lying length field from triggering a huge allocation up front
+        self.reader
+            .by_ref()
+            .take(remaining)
+            .read_to_end(&mut bytes)?;
+        self.position += (bytes.len() - CHUNK_SIZE) as u64;
+
+        if (bytes.len() as u64) < remaining + CHUNK_SIZE as u64 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(bytes)
+    }
+
+    /// Skips the rest of the stream as trailing data starting at `start`
+    fn skip_trailing(&mut self, start: u64) -> Option<Result<Chunk>> {
+        self.done = true;
+        match io::copy(&mut self.reader, &mut io::sink()) {
+            Ok(rest) => {
+                self.trailing_len = self.position - start + rest;
+                None
+            }
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+impl<R: Read> Iterator for ChunkReader<R> {
+    type Item = Result<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let start = self.position;
+        let bytes = match self.read_length() {
+            Ok(Some(length)) => self.read_chunk_bytes(length),
+            Ok(None) => {
+                self.done = true;
+                return None;
+            }
+            Err(e) => Err(e),
+        };
+
+        // after IEND, only bytes that can't be a chunk at all are trailing data, a damaged
+        // chunk is still reported
+        let chunk = match bytes {
+            Ok(bytes) if self.after_iend && !Chunk::is_framed(&bytes) => {
+                return self.skip_trailing(start)
+            }
+            Err(e) if self.after_iend && is_truncation(&e) => return self.skip_trailing(start),
+            Ok(bytes) => Chunk::parse(&bytes, self.check_crc, self.check_type),
+            Err(e) => Err(e),
+        };
+
+        match &chunk {
+            Ok(chunk) => self.after_iend |= chunk.chunk_type().bytes() == *b"IEND",
+            Err(_) => self.done = true,
+        }
+        Some(chunk)
+    }
+}
+
+/// Whether `e` comes from the stream ending before the chunk being read is complete
+fn is_truncation(e: &Error) -> bool {
+    matches!(e, Error::IO(e) if e.kind() == io::ErrorKind::UnexpectedEof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::io::Cursor;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        let chunks = vec![
+            Chunk::from_slice(ChunkType::from_str("FrSt").unwrap(), b"first"),
+            Chunk::from_slice(ChunkType::from_str("LASt").unwrap(), b"last"),
+        ];
+        Png::from_chunks(chunks).as_bytes()
+    }
+
+    #[test]
+    fn test_read_chunks() {
+        let reader = ChunkReader::new(Cursor::new(testing_bytes())).unwrap();
+        let chunks: Vec<Chunk> = reader.collect::<Result<_>>().unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].chunk_type().to_string(), "FrSt");
+        assert_eq!(chunks[1].data_as_string().unwrap(), "last");
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let mut png = Png::try_from(testing_bytes().as_ref()).unwrap();
+        png.append_chunk(Chunk::from_slice(ChunkType::from_str("IEND").unwrap(), b""));
+        png.append_chunk(Chunk::from_slice(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"msg",
+        ));
+        let mut bytes = png.as_bytes();
+        bytes.extend(b"junk");
+
+        let mut reader = ChunkReader::new(Cursor::new(bytes)).unwrap();
+        let chunks: Vec<Chunk> = reader.by_ref().collect::<Result<_>>().unwrap();
+        assert_eq!(chunks, png.chunks());
+        assert_eq!(reader.trailing_data_len(), 4);
+
+        // before IEND the same bytes are a broken chunk
+        let mut bytes = testing_bytes();
+        bytes.extend(b"junk");
+        let reader = ChunkReader::new(Cursor::new(bytes)).unwrap();
+        assert!(reader.collect::<Result<Vec<_>>>().is_err());
+    }
+
+    #[test]
+    fn test_corrupted_chunk_after_iend() {
+        let mut png = Png::try_from(testing_bytes().as_ref()).unwrap();
+        png.append_chunk(Chunk::from_slice(ChunkType::from_str("IEND").unwrap(), b""));
+        png.append_chunk(Chunk::from_slice(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"msg",
+        ));
+        let mut bytes = png.as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+
+        let mut reader = ChunkReader::new(Cursor::new(bytes)).unwrap();
+        assert!(reader.by_ref().collect::<Result<Vec<_>>>().is_err());
+        assert_eq!(reader.trailing_data_len(), 0);
+    }
+
+    #[test]
+    fn test_invalid_signature() {
+        let mut bytes = testing_bytes();
+        bytes[0] = 13;
+
+        assert!(ChunkReader::new(Cursor::new(bytes)).is_err());
+    }
+
+    #[test]
+    fn test_read_chunks_unchecked() {
+        let mut bytes = testing_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+
+        let reader = ChunkReader::new(Cursor::new(bytes.clone())).unwrap();
+        assert!(reader.collect::<Result<Vec<_>>>().is_err());
+
+        let reader = ChunkReader::new(Cursor::new(bytes))
+            .unwrap()
+            .check_crc(false);
+        let chunks: Vec<Chunk> = reader.collect::<Result<_>>().unwrap();
+        assert!(!chunks[1].crc_matches(chunks[1].crc()));
+    }
+
+    #[test]
+    fn test_read_chunks_with_invalid_type() {
+        let chunk = Chunk::from_slice(ChunkType::from_bytes_unchecked(*b"ab1\0"), b"odd");
+        let bytes = Png::from_chunks(vec![chunk]).as_bytes();
+
+        let reader = ChunkReader::new(Cursor::new(bytes.clone())).unwrap();
+        assert!(reader.collect::<Result<Vec<_>>>().is_err());
+
+        let reader = ChunkReader::new(Cursor::new(bytes))
+            .unwrap()
+            .check_type(false);
+        let chunks: Vec<Chunk> = reader.collect::<Result<_>>().unwrap();
+        assert_eq!(chunks[0].chunk_type().to_string(), "ab1\\x00");
+    }
+
+    #[test]
+    fn test_truncated_chunk() {
+        let mut bytes = testing_bytes();
+        bytes.truncate(bytes.len() - 2);
+
+        let mut reader = ChunkReader::new(Cursor::new(bytes)).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,113 @@
+//! Interactive terminal UI to browse the chunks of a PNG file
+//!
+//! The chunk list sits on the left, the data of the selected chunk on the right,
+//! as text when it is printable and as hex otherwise.
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::png::{Chunk, Png};
+use crate::{to_hex, Result};
+
+/// Opens the chunk browser on `png` until the user quits with `q` or `Esc`
+pub fn run(png: &Png) -> Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let result = App::new(png).run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+/// State of the chunk browser
+struct App<'a> {
+    png: &'a Png,
+    list: ListState,
+    /// Show the data as hex even when it is printable text
+    hex: bool,
+}
+
+impl<'a> App<'a> {
+    fn new(png: &'a Png) -> Self {
+        let mut list = ListState::default();
+        if !png.is_empty() {
+            list.select(Some(0));
+        }
+
+        App {
+            png,
+            list,
+            hex: false,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Down | KeyCode::Char('j') => self.list.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+                    KeyCode::Char('x') => self.hex = !self.hex,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [list_area, data_area] =
+            Layout::horizontal([Constraint::Length(32), Constraint::Min(0)]).areas(frame.area());
+
+        let items: Vec<ListItem> = self
+            .png
+            .chunks()
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                ListItem::new(format!(
+                    "#{} {} ({} bytes)",
+                    i,
+                    chunk.chunk_type(),
+                    chunk.length()
+                ))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(" Chunks "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+
+        let (title, data) = match self.list.selected().and_then(|i| self.png.chunk_at(i)) {
+            Some(chunk) => match printable_text(chunk) {
+                Some(text) if !self.hex => (" Data (text) ", text),
+                _ => (" Data (hex) ", to_hex(chunk.data())),
+            },
+            None => (" Data ", String::new()),
+        };
+        let data = Paragraph::new(data).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_bottom(Line::from(" ↑/↓ move  x toggle hex  q quit ")),
+        );
+        frame.render_widget(data, data_area);
+    }
+}
+
+/// The data as text, unless it is not UTF-8 or holds control characters that would
+/// garble the terminal
+fn printable_text(chunk: &Chunk) -> Option<String> {
+    chunk.data_as_string().ok().filter(|text| {
+        !text
+            .chars()
+            .any(|c| c.is_control() && c != '\n' && c != '\t')
+    })
+}
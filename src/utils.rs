@@ -2,7 +2,7 @@
 
 use crc::{Algorithm, Crc};
 
-use crate::CHUNK_SIZE;
+use crate::{Error, Result, CHUNK_SIZE};
 
 ///  Compute CRC32 using certian algorithm
 pub fn checksum_32(algo: &'static Algorithm<u32>, bytes: &[u8]) -> u32 {
@@ -14,3 +14,34 @@ pub fn checksum_32(algo: &'static Algorithm<u32>, bytes: &[u8]) -> u32 {
 pub fn u8_4_from_slice(arr: &[u8]) -> [u8; CHUNK_SIZE] {
     arr.try_into().expect("Invalid slice length")
 }
+
+/// Lowercase hex representation of bytes, two digits per byte
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parses hex digits into bytes, ignoring whitespace between them
+pub fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    let digits: Vec<char> = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(Error::Custom(format!(
+            "Invalid hex: {} digits given, two are needed per byte",
+            digits.len()
+        )));
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let byte: String = pair.iter().collect();
+            // from_str_radix alone would also accept a sign
+            match u8::from_str_radix(&byte, 16) {
+                Ok(value) if pair.iter().all(char::is_ascii_hexdigit) => Ok(value),
+                _ => Err(Error::Custom(format!(
+                    "Invalid hex: '{}' is not a byte",
+                    byte
+                ))),
+            }
+        })
+        .collect()
+}